@@ -0,0 +1,31 @@
+use crate::{sources, template::{self, Template}};
+use std::{collections::{BTreeMap, HashMap}, fs, io::{Error as IoError, ErrorKind, Result as IoResult}, path::PathBuf};
+
+/// Where the answers last given to a template are kept, next to the sources
+/// file.
+pub fn path(template: &Template) -> PathBuf {
+    return sources::dir_path().join("answers").join(&template.source).join(format!("{}.toml", template.name));
+}
+
+/// The answers last given to a template, which are none if it was never
+/// generated (or they were forgotten).
+pub fn load(template: &Template) -> IoResult<HashMap<String, String>> {
+    return match template::read_values(&path(template)) {
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(HashMap::new()),
+        result => result,
+    };
+}
+
+/// Remembers the answers to the template's variables for the next time,
+/// leaving out secrets.
+pub fn save(template: &Template, variables: &HashMap<String, String>) -> IoResult<()> {
+    let answers: BTreeMap<&String, &String> = template.manifest.variables.iter()
+        .filter(|variable| !variable.secret)
+        .filter_map(|variable| variables.get_key_value(&variable.name))
+        .collect();
+
+    let path = path(template);
+    fs::create_dir_all(path.parent().unwrap())?;
+    let content = toml::to_string(&answers).map_err(IoError::other)?;
+    return fs::write(path, content);
+}
@@ -0,0 +1,133 @@
+use chrono::{Datelike, Local, Timelike};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::{collections::HashMap, fs::{self, File, Metadata}, io::{self, Error as IoError, ErrorKind, Result as IoResult}, path::{Path, PathBuf}};
+use tar::{Archive, Builder, Header};
+use walkdir::WalkDir;
+use zip::{write::SimpleFileOptions, CompressionMethod, DateTime, ZipArchive, ZipWriter};
+
+pub fn is_archive(path: &Path) -> bool {
+    return stem(path).is_some();
+}
+
+// Returns the name of an archive with its extension removed, or nothing if
+// the file isn't an archive we know how to handle
+pub fn stem(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    for extension in [".tar.gz", ".tgz", ".zip"] {
+        if let Some(stem) = name.strip_suffix(extension) {
+            return Some(stem.to_string());
+        }
+    }
+
+    return None;
+}
+
+pub fn extract(path: &Path, output: &Path) -> IoResult<()> {
+    let file = File::open(path)?;
+    fs::create_dir_all(output)?;
+
+    if path.to_string_lossy().ends_with(".zip") {
+        let mut archive = ZipArchive::new(file).map_err(IoError::other)?;
+        return archive.extract(output).map_err(IoError::other);
+    } else if is_archive(path) {
+        return Archive::new(GzDecoder::new(file)).unpack(output);
+    }
+
+    return Err(IoError::new(ErrorKind::InvalidInput, "unsupported archive format"));
+}
+
+// Packs a directory into a gzipped tarball, with everything nested under a
+// single top-level directory so extracting it never scatters files around.
+// Extra files are generated in memory and replace any file at the same path,
+// while paths (relative to the directory) that are ignored are left out. Files
+// keep their own permission modes, unless they're given one in `modes`
+pub fn pack(path: &Path, root: &str, output: &Path, extra: &[(&str, &[u8])], modes: &HashMap<PathBuf, u32>, ignored: impl Fn(&Path, bool) -> bool) -> IoResult<()> {
+    let file = File::create(output)?;
+    let mut builder = Builder::new(GzEncoder::new(file, Compression::default()));
+    builder.follow_symlinks(false);
+
+    for entry in WalkDir::new(path) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(path).unwrap();
+        if extra.iter().any(|(name, _)| relative == Path::new(name)) || ignored(relative, entry.file_type().is_dir()) {
+            continue;
+        }
+
+        let name = Path::new(root).join(relative);
+        if entry.file_type().is_dir() {
+            builder.append_dir(name, entry.path())?;
+        } else if let Some(mode) = modes.get(relative) {
+            let mut header = Header::new_gnu();
+            header.set_metadata(&entry.metadata()?);
+            header.set_mode(*mode);
+            builder.append_data(&mut header, name, File::open(entry.path())?)?;
+        } else {
+            builder.append_path_with_name(entry.path(), name)?;
+        }
+    }
+
+    for (name, content) in extra {
+        let mut header = Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, Path::new(root).join(name), *content)?;
+    }
+
+    builder.into_inner()?.finish()?;
+    return Ok(());
+}
+
+// Packs a directory into whichever kind of archive the output is named after,
+// nested under a single top-level directory like pack does
+pub fn pack_as(path: &Path, root: &str, output: &Path) -> IoResult<()> {
+    if output.to_string_lossy().ends_with(".zip") {
+        return pack_zip(path, root, output);
+    } else if is_archive(output) {
+        return pack(path, root, output, &[], &HashMap::new(), |_, _| false);
+    }
+
+    return Err(IoError::new(ErrorKind::InvalidInput, "unsupported archive format"));
+}
+
+// Zip archives keep permission modes and symlinks too, for unzip to restore
+// them on unix
+fn pack_zip(path: &Path, root: &str, output: &Path) -> IoResult<()> {
+    let mut writer = ZipWriter::new(File::create(output)?);
+    // Entries would otherwise all date from 1980, the earliest zip allows
+    let now = Local::now();
+    let time = DateTime::from_date_and_time(now.year() as u16, now.month() as u8, now.day() as u8, now.hour() as u8, now.minute() as u8, now.second() as u8)
+        .unwrap_or_default();
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated).last_modified_time(time);
+
+    for entry in WalkDir::new(path) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(path).unwrap();
+        // Zip entries are always separated with forward slashes
+        let name = Path::new(root).join(relative).to_string_lossy().replace('\\', "/");
+        let options = options.unix_permissions(mode(&entry.metadata()?));
+
+        if entry.path_is_symlink() {
+            writer.add_symlink(name, fs::read_link(entry.path())?.to_string_lossy(), options).map_err(IoError::other)?;
+        } else if entry.file_type().is_dir() {
+            writer.add_directory(name, options).map_err(IoError::other)?;
+        } else {
+            writer.start_file(name, options).map_err(IoError::other)?;
+            io::copy(&mut File::open(entry.path())?, &mut writer)?;
+        }
+    }
+
+    writer.finish().map_err(IoError::other)?;
+    return Ok(());
+}
+
+#[cfg(unix)]
+fn mode(metadata: &Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    return metadata.permissions().mode() & 0o7777;
+}
+
+#[cfg(not(unix))]
+fn mode(metadata: &Metadata) -> u32 {
+    return if metadata.is_dir() { 0o755 } else { 0o644 };
+}
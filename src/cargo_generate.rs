@@ -0,0 +1,240 @@
+use crate::template::{FileRule, Kind, Manifest, Variable, MANIFEST};
+use regex::{Captures, Regex};
+use std::{env, fs, io::{Error as IoError, ErrorKind, Result as IoResult}, path::Path, sync::OnceLock};
+
+pub const CONFIG: &str = "cargo-generate.toml";
+// The syntax cargo-generate templates are written in, see liquid
+pub const SYNTAX: &str = "liquid";
+// Files cargo-generate renders, dropping this extension from their name
+pub const EXTENSION: &str = ".liquid";
+
+/// Whether a directory holds a cargo-generate template, which a manifest of
+/// its own turns into a regular template again. Not every such template has
+/// a `cargo-generate.toml`, the ones without are told apart by a Cargo.toml
+/// naming the project with a placeholder.
+pub fn is_cargo_generate(dir: &Path) -> bool {
+    if dir.join(MANIFEST).is_file() {
+        return false;
+    } else if dir.join(CONFIG).is_file() {
+        return true;
+    }
+
+    return ["Cargo.toml", "Cargo.toml.liquid"].iter()
+        .filter_map(|name| fs::read_to_string(dir.join(name)).ok())
+        .any(|content| content.contains("project-name"));
+}
+
+/// Reads a cargo-generate template as a srcinit one, returning the manifest
+/// its `cargo-generate.toml` (if any) amounts to. Files keep their Liquid
+/// placeholders, which are read as Tera when rendering (see [`liquid`]).
+///
+/// Placeholders become variables, named with underscores instead of dashes,
+/// and the ones cargo-generate always provides (`crate_name`, `authors`...)
+/// become computed values. Ignored and conditionally ignored files become
+/// file rules, while hooks (Rhai scripts) are left out.
+pub fn convert(dir: &Path) -> IoResult<Manifest> {
+    let config: toml::Table = match fs::read_to_string(dir.join(CONFIG)) {
+        Ok(content) => toml::from_str(&content)
+            .map_err(|error| IoError::new(ErrorKind::InvalidData, format!("invalid {}: {}", CONFIG, error.message())))?,
+        Err(error) if error.kind() == ErrorKind::NotFound => toml::Table::new(),
+        Err(error) => return Err(error),
+    };
+    let table = |name: &str| config.get(name).and_then(toml::Value::as_table).cloned().unwrap_or_default();
+    let strings = |table: &toml::Table, name: &str| -> Vec<String> {
+        return table.get(name).and_then(toml::Value::as_array).into_iter().flatten().filter_map(|value| value.as_str().map(String::from)).collect();
+    };
+
+    let mut manifest = Manifest { syntax: Some(SYNTAX.to_string()), ..Manifest::default() };
+    for (name, placeholder) in table("placeholders") {
+        let Some(placeholder) = placeholder.as_table() else {
+            continue;
+        };
+
+        let choices = strings(placeholder, "choices");
+        let kind = match placeholder.get("type").and_then(toml::Value::as_str) {
+            Some("bool") => Kind::Bool,
+            _ if !choices.is_empty() => Kind::Choice,
+            _ => Kind::String,
+        };
+        manifest.variables.push(Variable {
+            name: name.replace('-', "_"),
+            kind,
+            default: placeholder.get("default").cloned(),
+            help: placeholder.get("prompt").and_then(toml::Value::as_str).map(String::from),
+            choices,
+            pattern: placeholder.get("regex").and_then(toml::Value::as_str).map(String::from),
+            ..Variable::default()
+        });
+    }
+
+    let os_arch = format!("{}-{}", env::consts::OS, env::consts::ARCH);
+    for (name, value) in [
+        ("crate_name", toml::Value::String(String::from("{{ project_name | snake_case }}"))),
+        ("authors", toml::Value::String(String::from("{{ git_name }}{% if git_email %} <{{ git_email }}>{% endif %}"))),
+        ("username", toml::Value::String(String::from("{{ git_name }}"))),
+        ("crate_type", toml::Value::String(String::from("bin"))),
+        ("os_arch", toml::Value::String(os_arch)),
+        ("within_cargo_project", toml::Value::Boolean(false)),
+        ("is_init", toml::Value::Boolean(false)),
+    ] {
+        manifest.computed.insert(name.to_string(), value);
+    }
+
+    let template = table("template");
+    let hooks = table("hooks");
+    let never = [CONFIG.to_string(), String::from(".git")].into_iter()
+        .chain(strings(&template, "ignore"))
+        .chain(["init", "pre", "post"].iter().flat_map(|stage| strings(&hooks, stage)));
+    for path in never {
+        manifest.files.push(FileRule { path, when: Some(String::from("false")) });
+    }
+    for (condition, rules) in table("conditional") {
+        let when = rhai(&condition, true);
+        for path in rules.as_table().map(|rules| strings(rules, "ignore")).unwrap_or_default() {
+            manifest.files.push(FileRule { path, when: Some(when.clone()) });
+        }
+    }
+
+    return Ok(manifest);
+}
+
+// Conditions are Rhai expressions, which mostly differ from Tera ones in
+// their operators. Tera can't negate a parenthesized expression though, so
+// negations are pushed down to each value instead (`!(a && b)` is read as
+// `(not a or not b)`)
+fn rhai(condition: &str, negate: bool) -> String {
+    let condition = condition.trim();
+    for (operator, and, or) in [("||", " or ", " and "), ("&&", " and ", " or ")] {
+        let parts = split(condition, operator);
+        if parts.len() > 1 {
+            let parts: Vec<String> = parts.iter().map(|part| rhai(part, negate)).collect();
+            return format!("({})", parts.join(if negate { or } else { and }));
+        }
+    }
+
+    if let Some(inner) = condition.strip_prefix('!').filter(|inner| !inner.starts_with('=')) {
+        return rhai(inner, !negate);
+    } else if let Some(inner) = group(condition) {
+        return rhai(inner, negate);
+    }
+
+    let value = identifiers(condition);
+    return if negate { format!("not {}", value) } else { value };
+}
+
+// What's inside the parentheses around a whole expression, if it is one
+// (unlike `(a) == (b)`)
+fn group(expression: &str) -> Option<&str> {
+    let inner = expression.strip_prefix('(')?.strip_suffix(')')?;
+    let mut depth = 0;
+    for char in inner.chars() {
+        depth += match char { '(' => 1, ')' => -1, _ => 0 };
+        if depth < 0 {
+            return None;
+        }
+    }
+    return Some(inner);
+}
+
+// Splits an expression on an operator, except where it appears inside
+// parentheses or strings
+fn split<'a>(expression: &'a str, operator: &str) -> Vec<&'a str> {
+    let (mut parts, mut depth, mut quote, mut start) = (Vec::new(), 0, None, 0);
+    for (index, char) in expression.char_indices() {
+        match (char, quote) {
+            ('"' | '\'', None) => quote = Some(char),
+            (char, Some(open)) if char == open => quote = None,
+            (_, Some(_)) => {}
+            ('(', None) => depth += 1,
+            (')', None) => depth -= 1,
+            _ if depth == 0 && index >= start && expression[index..].starts_with(operator) => {
+                parts.push(&expression[start..index]);
+                start = index + operator.len();
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(&expression[start..]);
+    return parts;
+}
+
+/// Turns Liquid (as written for cargo-generate) into the Tera it stands
+/// for: placeholders named with dashes, Liquid's filter names & arguments,
+/// and `elsif` & `unless` tags. Anything inside `{% raw %}` stays as is.
+pub fn liquid(text: &str) -> String {
+    static TAGS: OnceLock<Regex> = OnceLock::new();
+    let tags = TAGS.get_or_init(|| Regex::new(r"(?s)\{%-?\s*raw\s*-?%\}.*?\{%-?\s*endraw\s*-?%\}|\{\{.*?\}\}|\{%.*?%\}").unwrap());
+
+    return tags.replace_all(text, |captures: &Captures| {
+        let tag = &captures[0];
+        if tag.starts_with("{%") && tag.trim_start_matches("{%").trim_start_matches('-').trim_start().starts_with("raw") {
+            return tag.to_string();
+        }
+
+        let tag = identifiers(tag);
+        let tag = Regex::new(r"\{%(-?)\s*elsif\b").unwrap().replace(&tag, "{%$1 elif");
+        let tag = Regex::new(r"\{%(-?)\s*endunless\b").unwrap().replace(&tag, "{%$1 endif");
+        let tag = Regex::new(r"(?s)\{%(-?)\s*unless\s+(.*?)\s*(-?)%\}").unwrap().replace(&tag, "{%$1 if not ($2) $3%}");
+        return filters(&tag);
+    }).to_string();
+}
+
+// Names with dashes (like project-name) are subtractions to Tera, so they're
+// read with underscores instead, leaving strings alone
+fn identifiers(text: &str) -> String {
+    static IDENTIFIERS: OnceLock<Regex> = OnceLock::new();
+    let identifiers = IDENTIFIERS.get_or_init(|| Regex::new(r#""[^"]*"|'[^']*'|\b[A-Za-z_]\w*(?:-\w+)+\b"#).unwrap());
+
+    return identifiers.replace_all(text, |captures: &Captures| match captures[0].starts_with(['"', '\'']) {
+        true => captures[0].to_string(),
+        false => captures[0].replace('-', "_"),
+    }).to_string();
+}
+
+fn filters(tag: &str) -> String {
+    static FILTERS: OnceLock<Regex> = OnceLock::new();
+    let filters = FILTERS.get_or_init(|| Regex::new(r#"\|\s*(\w+)(?:\s*:\s*("[^"]*"|'[^']*')\s*,\s*("[^"]*"|'[^']*'))?"#).unwrap());
+
+    return filters.replace_all(tag, |captures: &Captures| {
+        let name = match &captures[1] {
+            "upcase" => "upper",
+            "downcase" => "lower",
+            "strip" => "trim",
+            "size" => "length",
+            "upper_camel_case" => "pascal_case",
+            "lower_camel_case" => "camel_case",
+            "shouty_snake_case" => "screaming_snake_case",
+            "title_case" => "title",
+            name => name,
+        };
+        return match (captures.get(2), captures.get(3)) {
+            (Some(from), Some(to)) => format!("| {}(from={}, to={})", name, from.as_str(), to.as_str()),
+            _ => format!("| {}", name),
+        };
+    }).to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_rhai_conditions() {
+        assert_eq!(rhai("use-serde", false), "use_serde");
+        assert_eq!(rhai("crate_type == \"lib\" && !is-async", false), "(crate_type == \"lib\" and not is_async)");
+        assert_eq!(rhai("!(a || b)", false), "(not a and not b)");
+        assert_eq!(rhai("a != \"x || y\"", false), "a != \"x || y\"");
+        assert_eq!(rhai("(a) == (b)", false), "(a) == (b)");
+    }
+
+    #[test]
+    fn converts_liquid() {
+        assert_eq!(liquid("{{ project-name | upcase }}"), "{{ project_name | upper }}");
+        assert_eq!(liquid("{{ crate_name | replace: \"-\", \"_\" }}"), "{{ crate_name | replace(from=\"-\", to=\"_\") }}");
+        assert_eq!(liquid("{% if a %}1{% elsif b %}2{% endif %}"), "{% if a %}1{% elif b %}2{% endif %}");
+        assert_eq!(liquid("{% unless is-lib %}bin{% endunless %}"), "{% if not (is_lib) %}bin{% endif %}");
+        assert_eq!(liquid("{% raw %}{{ project-name }}{% endraw %}"), "{% raw %}{{ project-name }}{% endraw %}");
+        assert_eq!(liquid("{{ \"project-name\" }}"), "{{ \"project-name\" }}");
+    }
+}
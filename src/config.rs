@@ -0,0 +1,40 @@
+use crate::sources;
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, io::{Error as IoError, ErrorKind, Result as IoResult}, path::PathBuf};
+
+/// Preferences of the current user, read from `config.toml` next to the
+/// sources file. Every setting is optional.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub generate: GenerateConfig,
+    /// The `[defaults]` table, answers every template starts out with for
+    /// variables of the same name (e.g. `author`, `email` or `license`).
+    pub defaults: BTreeMap<String, String>,
+}
+
+/// The `[generate]` table, defaults for `srcinit generate`.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct GenerateConfig {
+    /// Initialize a git repository in every generated project.
+    pub git: bool,
+}
+
+impl Config {
+    pub fn path() -> PathBuf {
+        return sources::dir_path().join("config.toml");
+    }
+
+    /// Reads the user's config, which is simply empty when there's none.
+    pub fn load() -> IoResult<Config> {
+        let content = match fs::read_to_string(Config::path()) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+            Err(error) => return Err(error),
+        };
+
+        return toml::from_str(&content)
+            .map_err(|error| IoError::new(ErrorKind::InvalidData, format!("invalid config.toml: {}", error.message())));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::template::{Kind, Manifest, Variable, MANIFEST};
+use log::debug;
+use regex::Regex;
+use serde::{de::{MapAccess, Visitor}, Deserialize, Deserializer};
+use serde_json::Value;
+use std::{fmt::{Formatter, Result as FmtResult}, fs, io::{Error as IoError, ErrorKind, Result as IoResult}, path::{Path, PathBuf}};
+
+pub const CONFIG: &str = "cookiecutter.json";
+// What cookiecutter templates find the answers under, as in
+// `{{ cookiecutter.project_name }}`
+pub const NAMESPACE: &str = "cookiecutter";
+
+/// Whether a directory holds a cookiecutter template, which a manifest of
+/// its own turns into a regular template again.
+pub fn is_cookiecutter(dir: &Path) -> bool {
+    return dir.join(CONFIG).is_file() && !dir.join(MANIFEST).is_file();
+}
+
+/// Reads a cookiecutter template as a srcinit one, returning the manifest
+/// its `cookiecutter.json` amounts to and the directory its files are in.
+///
+/// Plain values become variables (lists become choices), while values
+/// rendered from other ones become computed values, which can still be
+/// answered with --define. Private values (starting with an underscore)
+/// and hooks are left out.
+pub fn convert(dir: &Path) -> IoResult<(Manifest, PathBuf)> {
+    let invalid = |message: String| IoError::new(ErrorKind::InvalidData, format!("invalid {}: {}", CONFIG, message));
+    let Ordered(config) = serde_json::from_slice(&fs::read(dir.join(CONFIG))?).map_err(|error| invalid(error.to_string()))?;
+    let prompts = config.iter().find(|(name, _)| name == "__prompts__").and_then(|(_, prompts)| prompts.as_object()).cloned().unwrap_or_default();
+
+    let mut manifest = Manifest { namespace: Some(NAMESPACE.to_string()), ..Manifest::default() };
+    for (name, value) in config {
+        if name.starts_with('_') {
+            continue;
+        }
+
+        // A prompt is either the question itself, or a question along with
+        // labels for each choice
+        let help = prompts.get(&name)
+            .and_then(|prompt| prompt.as_str().or_else(|| prompt.get("__prompt__").and_then(Value::as_str)))
+            .map(String::from);
+        let (kind, default, choices) = match value {
+            Value::String(text) if text.contains("{{") || text.contains("{%") => {
+                manifest.computed.insert(name, toml::Value::String(expression(&text)));
+                continue;
+            }
+            Value::String(text) => (Kind::String, toml::Value::String(text), Vec::new()),
+            Value::Bool(value) => (Kind::Bool, toml::Value::Boolean(value), Vec::new()),
+            Value::Number(number) => match number.as_i64() {
+                Some(number) => (Kind::Int, toml::Value::Integer(number), Vec::new()),
+                None => (Kind::String, toml::Value::String(number.to_string()), Vec::new()),
+            },
+            Value::Array(choices) if !choices.is_empty() => {
+                let choices: Vec<String> = choices.iter().map(|choice| choice.as_str().map(String::from).unwrap_or(choice.to_string())).collect();
+                (Kind::Choice, toml::Value::String(choices[0].clone()), choices)
+            }
+            _ => {
+                debug!("Skipping {} of {}, it has no srcinit equivalent", name, CONFIG);
+                continue;
+            }
+        };
+        manifest.variables.push(Variable { name, kind, default: Some(default), help, choices, ..Variable::default() });
+    }
+
+    return Ok((manifest, root(dir)?));
+}
+
+// The files of a cookiecutter template are all inside a directory named
+// after the project (e.g. "{{cookiecutter.project_slug}}"), which is what
+// srcinit's output directory stands for
+fn root(dir: &Path) -> IoResult<PathBuf> {
+    let roots: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter(|entry| entry.file_name().to_string_lossy().contains("{{") && entry.file_name().to_string_lossy().contains(NAMESPACE))
+        .map(|entry| entry.path())
+        .collect();
+
+    return match roots.as_slice() {
+        [root] => Ok(root.clone()),
+        _ => Err(IoError::new(ErrorKind::InvalidData, "cookiecutter templates need a single \"{{cookiecutter.*}}\" directory")),
+    };
+}
+
+// Cookiecutter renders with Jinja, where values are derived with bits of
+// Python that Tera has filters for instead
+fn expression(text: &str) -> String {
+    static RULES: &[(&str, &str)] = &[
+        (r"\bcookiecutter\.", ""),
+        (r"\.lower\(\)", " | lower"),
+        (r"\.upper\(\)", " | upper"),
+        (r"\.strip\(\)", " | trim"),
+        (r"\.title\(\)", " | title"),
+        (r"\.capitalize\(\)", " | capitalize"),
+        (r#"\.replace\(\s*('[^']*'|"[^"]*")\s*,\s*('[^']*'|"[^"]*")\s*\)"#, " | replace(from=$1, to=$2)"),
+    ];
+
+    let mut expression = text.to_string();
+    for (pattern, replacement) in RULES {
+        expression = Regex::new(pattern).unwrap().replace_all(&expression, *replacement).to_string();
+    }
+    return expression;
+}
+
+// cookiecutter.json is read in the order it's written, which is the order
+// variables are asked for (and derived values can only use earlier ones)
+struct Ordered(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for Ordered {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Ordered, D::Error> {
+        struct OrderedVisitor;
+
+        impl<'de> Visitor<'de> for OrderedVisitor {
+            type Value = Ordered;
+
+            fn expecting(&self, formatter: &mut Formatter) -> FmtResult {
+                return formatter.write_str("an object");
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Ordered, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                return Ok(Ordered(entries));
+            }
+        }
+
+        return deserializer.deserialize_map(OrderedVisitor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_expressions() {
+        assert_eq!(expression("cookiecutter.project_name"), "project_name");
+        assert_eq!(expression("cookiecutter.project_name.lower().strip()"), "project_name | lower | trim");
+        assert_eq!(expression("cookiecutter.project_name.replace(' ', '_')"), "project_name | replace(from=' ', to='_')");
+        assert_eq!(expression("cookiecutter.name.title() ~ \"!\""), "name | title ~ \"!\"");
+    }
+}
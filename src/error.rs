@@ -0,0 +1,60 @@
+use crate::sources::remote;
+use std::io::{Error as IoError, ErrorKind};
+use thiserror::Error;
+
+/// Everything a srcinit command can fail with.
+///
+/// Messages are the reason alone (e.g. "Does not exist"); saying what
+/// failed is left to the caller.
+#[derive(Debug, Error)]
+pub enum SrcinitError {
+    #[error("{0}")]
+    Io(IoError),
+    #[error("{0}")]
+    Network(IoError),
+    #[error("{0}")]
+    TemplateNotFound(String),
+    #[error("{0}")]
+    Source(String),
+    #[error("{0}")]
+    Validation(String),
+    #[error("Aborted")]
+    Aborted,
+}
+
+impl SrcinitError {
+    // See "Exit codes" in README.md, which scripts rely on
+    pub fn exit_code(&self) -> i32 {
+        return match self {
+            SrcinitError::Validation(..) => 1,
+            SrcinitError::TemplateNotFound(..) => 2,
+            SrcinitError::Source(..) | SrcinitError::Network(..) => 3,
+            SrcinitError::Io(..) => 4,
+            SrcinitError::Aborted => 5,
+        };
+    }
+
+    pub fn validation(message: impl Into<String>) -> SrcinitError {
+        return SrcinitError::Validation(message.into());
+    }
+}
+
+// Library functions report everything as IO errors, so anything that came
+// from a server is picked out here
+impl From<IoError> for SrcinitError {
+    fn from(error: IoError) -> SrcinitError {
+        if remote::is_network(&error) {
+            return SrcinitError::Network(error);
+        } else if error.kind() == ErrorKind::InvalidInput {
+            return SrcinitError::Validation(error.to_string());
+        }
+
+        return SrcinitError::Io(error);
+    }
+}
+
+impl From<dialoguer::Error> for SrcinitError {
+    fn from(dialoguer::Error::IO(error): dialoguer::Error) -> SrcinitError {
+        return SrcinitError::from(error);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{config::Config, gitignore, license::{self, License}, lock::Lock, template::{self, Conflict, Extra, Filter, Template}, upgrade::{self, Outcome}};
+use log::debug;
+use std::{collections::HashMap, fs, io::{Error as IoError, ErrorKind, Result as IoResult}, path::{Path, PathBuf}};
+
+/// Renders a template into an output directory.
+///
+/// Variables start out with the template defaults (plus `project_name`,
+/// named after the output directory), then answers can be layered on top
+/// from a values file, explicit definitions, or interactive prompts.
+pub struct Generator<'a> {
+    template: &'a Template,
+    output: PathBuf,
+    variables: HashMap<String, String>,
+    answered: Vec<String>,
+    conflict: Option<Conflict>,
+    follow_symlinks: bool,
+    filter: Filter,
+    license: Option<String>,
+    skip_lock: bool,
+}
+
+impl<'a> Generator<'a> {
+    pub fn new(template: &'a Template, output: impl Into<PathBuf>) -> Generator<'a> {
+        let output = output.into();
+        let project_name = fs::canonicalize(&output)
+            .unwrap_or(output.clone())
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or(template.name.clone());
+
+        let variables = template.variables(HashMap::from([
+            (String::from("project_name"), project_name),
+        ]));
+        return Generator { template, output, variables, answered: Vec::new(), conflict: None, follow_symlinks: false, filter: Filter::default(), license: None, skip_lock: false };
+    }
+
+    pub fn template(&self) -> &Template {
+        return self.template;
+    }
+
+    pub fn output(&self) -> &Path {
+        return &self.output;
+    }
+
+    pub fn variables(&self) -> &HashMap<String, String> {
+        return &self.variables;
+    }
+
+    /// Sets the value a variable of the template starts out with, which is
+    /// still prompted for. Names the template doesn't declare are ignored.
+    pub fn prefill(&mut self, name: &str, value: &str) -> &mut Self {
+        if self.template.manifest.variables.iter().any(|variable| variable.name == name) {
+            self.variables.insert(name.to_string(), value.to_string());
+        }
+        return self;
+    }
+
+    /// Sets a variable, which is then never prompted for.
+    pub fn define(&mut self, name: &str, value: &str) -> &mut Self {
+        self.variables.insert(name.to_string(), value.to_string());
+        self.answered.push(name.to_string());
+        return self;
+    }
+
+    /// Sets every variable of a TOML or JSON values file.
+    pub fn values(&mut self, path: &Path) -> IoResult<&mut Self> {
+        for (name, value) in template::read_values(path)? {
+            self.define(&name, &value);
+        }
+        return Ok(self);
+    }
+
+    /// Asks for every variable that wasn't set explicitly.
+    pub fn prompt(&mut self) -> IoResult<&mut Self> {
+        let answered: Vec<&str> = self.answered.iter().map(String::as_str).collect();
+        self.variables = self.template.prompt(self.variables.clone(), &answered)?;
+        return Ok(self);
+    }
+
+    /// Checks every answer against the type & constraints of its variable.
+    pub fn validate(&self) -> IoResult<()> {
+        return self.template.validate(&self.variables);
+    }
+
+    /// Variables without a value, which have to be set before generating.
+    pub fn missing(&self) -> Vec<String> {
+        return self.template.missing(&self.variables);
+    }
+
+    /// The template's message for after generating, rendered with the
+    /// answers, if it has one.
+    pub fn post_message(&self) -> IoResult<Option<String>> {
+        return self.template.post_message(&self.variables);
+    }
+
+    /// Allows generating into a non-empty output directory, handling files
+    /// that already exist there as `conflict` says.
+    pub fn on_conflict(&mut self, conflict: Conflict) -> &mut Self {
+        self.conflict = Some(conflict);
+        return self;
+    }
+
+    /// Generates what symlinks in the template point to, instead of the
+    /// symlinks themselves (which Windows always does).
+    pub fn follow_symlinks(&mut self) -> &mut Self {
+        self.follow_symlinks = true;
+        return self;
+    }
+
+    /// Only generates the files the filter matches. Since the project then
+    /// only holds part of the template, no `.srcinit.lock` is written.
+    pub fn filter(&mut self, filter: Filter) -> &mut Self {
+        self.filter = filter;
+        return self;
+    }
+
+    /// Doesn't record anything in a `.srcinit.lock` when generating.
+    pub fn skip_lock(&mut self) -> &mut Self {
+        self.skip_lock = true;
+        return self;
+    }
+
+    /// Writes a LICENSE file with the license of that SPDX identifier (or
+    /// none, for "none"), instead of the one the manifest asks for.
+    pub fn license(&mut self, id: &str) -> &mut Self {
+        self.license = Some(id.to_string());
+        return self;
+    }
+
+    /// The files generating would create, relative to the output directory
+    /// and with placeholders in their names rendered, without writing anything.
+    pub fn plan(&self) -> IoResult<Vec<PathBuf>> {
+        let context = self.template.context(&self.variables)?;
+        let entries = template::plan(self.template, &mut template::engine(), &context, self.follow_symlinks, &self.filter)?;
+        let mut files: Vec<PathBuf> = entries.into_iter().filter(|entry| !entry.is_dir).map(|entry| entry.target).collect();
+        if self.licensed()?.is_some() && !files.iter().any(|file| file == Path::new(license::FILE)) {
+            files.push(PathBuf::from(license::FILE));
+        }
+        if !self.gitignored()?.is_empty() && !files.iter().any(|file| file == Path::new(gitignore::FILE)) {
+            files.push(PathBuf::from(gitignore::FILE));
+        }
+        return Ok(files);
+    }
+
+    /// Like [`Generator::plan`], along with the size each file will have once
+    /// rendered.
+    pub fn preview(&self) -> IoResult<Vec<(PathBuf, u64)>> {
+        let context = self.template.context(&self.variables)?;
+        let mut tera = template::engine();
+        let entries = template::plan(self.template, &mut tera, &context, self.follow_symlinks, &self.filter)?;
+        let sizes = template::sizes(self.template, &mut tera, &context, &entries)?;
+        let files = entries.into_iter().filter(|entry| !entry.is_dir).map(|entry| entry.target);
+        let mut files: Vec<(PathBuf, u64)> = files.zip(sizes).collect();
+        if let Some(license) = self.licensed()? {
+            files.retain(|(file, _)| file != Path::new(license::FILE));
+            files.push((PathBuf::from(license::FILE), license.text(&builtin("year"), &self.holder()?).len() as u64));
+        }
+        // Composed on top of the template's own .gitignore, if it has one
+        let names = self.gitignored()?;
+        if !names.is_empty() {
+            let size = gitignore::compose(&names, "")?.len() as u64;
+            match files.iter_mut().find(|(file, _)| file == Path::new(gitignore::FILE)) {
+                Some((_, existing)) => *existing += size + 1,
+                None => files.push((PathBuf::from(gitignore::FILE), size)),
+            }
+        }
+        return Ok(files);
+    }
+
+    /// Renders the template, then records it along with the answers in
+    /// the project's `.srcinit.lock`.
+    pub fn generate(&self) -> IoResult<()> {
+        self.validate()?;
+        template::generate(self.template, &self.output, &self.variables, self.conflict, self.follow_symlinks, &self.filter, &self.extras()?)?;
+        if self.skip_lock || !self.filter.is_empty() {
+            return Ok(());
+        }
+        return self.lock();
+    }
+
+    /// Renders an add-on template into an existing project, leaving its
+    /// `.srcinit.lock` (which belongs to the template the project was
+    /// generated from) alone.
+    pub fn add(&self) -> IoResult<()> {
+        self.validate()?;
+        return template::generate(self.template, &self.output, &self.variables, self.conflict, self.follow_symlinks, &self.filter, &[]);
+    }
+
+    /// Brings an already generated project (the output directory) up to date
+    /// with the template, merging template changes since the version in its
+    /// lockfile with the project's own changes. Returns every file touched.
+    pub fn upgrade(&self) -> IoResult<Vec<(PathBuf, Outcome)>> {
+        let outcomes = upgrade::upgrade(&self.output, self.template, &self.variables)?;
+        self.lock()?;
+        return Ok(outcomes);
+    }
+
+    // The files written along with the template's: its LICENSE and its
+    // composed .gitignore
+    fn extras(&self) -> IoResult<Vec<Extra<'static>>> {
+        let mut extras: Vec<Extra> = Vec::new();
+        if let Some(license) = self.licensed()? {
+            // A LICENSE the project already has is only replaced when
+            // overwriting, like any other file
+            match self.output.join(license::FILE).exists() && self.conflict != Some(Conflict::Overwrite) {
+                true => debug!("Keeping existing \"{}\"", license::FILE),
+                false => {
+                    let text = license.text(&builtin("year"), &self.holder()?);
+                    extras.push((PathBuf::from(license::FILE), Box::new(move |_| Ok(Some(text.clone())))));
+                }
+            }
+        }
+        // Composing only ever adds rules, so the project's own .gitignore
+        // (or the template's) is always kept
+        let gitignored = self.gitignored()?;
+        if !gitignored.is_empty() {
+            extras.push((PathBuf::from(gitignore::FILE), Box::new(move |existing| {
+                gitignore::compose(&gitignored, &existing.unwrap_or_default()).map(Some)
+            })));
+        }
+        return Ok(extras);
+    }
+
+    // The license to write, given to license or asked for by the manifest,
+    // unless the filter leaves LICENSE out
+    fn licensed(&self) -> IoResult<Option<&'static License>> {
+        let id = match (&self.license, &self.template.manifest.license) {
+            (Some(id), _) => id.clone(),
+            (None, Some(id)) => template::render(&mut template::engine(), id, &self.template.context(&self.variables)?)
+                .map_err(|error| IoError::new(error.kind(), format!("license: {}", error)))?,
+            (None, None) => return Ok(None),
+        };
+        if id.trim().is_empty() || id.trim().eq_ignore_ascii_case("none") || !self.filter.matches(Path::new(license::FILE)) {
+            return Ok(None);
+        }
+
+        return license::find(&id).map(Some).ok_or_else(|| IoError::new(ErrorKind::InvalidInput, format!("unknown license \"{}\"", id)));
+    }
+
+    // The sets of rules the manifest composes into .gitignore, checked to
+    // exist, unless the filter leaves .gitignore out
+    fn gitignored(&self) -> IoResult<Vec<String>> {
+        if self.template.manifest.gitignore.is_empty() || !self.filter.matches(Path::new(gitignore::FILE)) {
+            return Ok(Vec::new());
+        }
+
+        let context = self.template.context(&self.variables)?;
+        let mut names = Vec::new();
+        for name in &self.template.manifest.gitignore {
+            let rendered = template::render(&mut template::engine(), name, &context)
+                .map_err(|error| IoError::new(error.kind(), format!("gitignore: {}", error)))?;
+            names.extend(rendered.split(',').map(str::trim).filter(|name| !name.is_empty() && !name.eq_ignore_ascii_case("none")).map(String::from));
+        }
+
+        gitignore::compose(&names, "")?;
+        return Ok(names);
+    }
+
+    // Who the copyright is held by: the author, as answered or as set in the
+    // [defaults] of config.toml, falling back to the git user
+    fn holder(&self) -> IoResult<String> {
+        let author = match self.variables.get("author") {
+            Some(author) => Some(author.clone()),
+            None => Config::load()?.defaults.get("author").cloned(),
+        };
+        return Ok([author.unwrap_or_default(), builtin("git_name"), builtin("user")].into_iter().find(|holder| !holder.trim().is_empty()).unwrap_or_default());
+    }
+
+    fn lock(&self) -> IoResult<()> {
+        let mut lock = Lock::new(self.template, &self.variables);
+        lock.checksum = Some(template::snapshot(self.template)?);
+        return lock.write(&self.output);
+    }
+}
+
+fn builtin(name: &str) -> String {
+    return template::builtins().iter().find(|(key, _)| *key == name).map(|(_, value)| value.clone()).unwrap_or_default();
+}
@@ -0,0 +1,109 @@
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+
+/// The file gitignores are composed into in generated projects.
+pub const FILE: &str = ".gitignore";
+
+/// Ignore rules for a language, an OS or an editor, which a `.gitignore` can
+/// be composed from.
+pub struct Gitignore {
+    pub name: &'static str,
+    pub title: &'static str,
+    text: &'static str,
+}
+
+pub const GITIGNORES: &[Gitignore] = &[
+    Gitignore { name: "c", title: "C & C++", text: include_str!("gitignores/c.gitignore") },
+    Gitignore { name: "emacs", title: "Emacs", text: include_str!("gitignores/emacs.gitignore") },
+    Gitignore { name: "go", title: "Go", text: include_str!("gitignores/go.gitignore") },
+    Gitignore { name: "java", title: "Java (Maven & Gradle)", text: include_str!("gitignores/java.gitignore") },
+    Gitignore { name: "jetbrains", title: "JetBrains IDEs", text: include_str!("gitignores/jetbrains.gitignore") },
+    Gitignore { name: "linux", title: "Linux", text: include_str!("gitignores/linux.gitignore") },
+    Gitignore { name: "macos", title: "macOS", text: include_str!("gitignores/macos.gitignore") },
+    Gitignore { name: "node", title: "Node.js", text: include_str!("gitignores/node.gitignore") },
+    Gitignore { name: "python", title: "Python", text: include_str!("gitignores/python.gitignore") },
+    Gitignore { name: "rust", title: "Rust", text: include_str!("gitignores/rust.gitignore") },
+    Gitignore { name: "vim", title: "Vim", text: include_str!("gitignores/vim.gitignore") },
+    Gitignore { name: "vscode", title: "Visual Studio Code", text: include_str!("gitignores/vscode.gitignore") },
+    Gitignore { name: "windows", title: "Windows", text: include_str!("gitignores/windows.gitignore") },
+];
+
+/// Looks a set of rules up by name, ignoring case.
+pub fn find(name: &str) -> Option<&'static Gitignore> {
+    return GITIGNORES.iter().find(|gitignore| gitignore.name.eq_ignore_ascii_case(name.trim()));
+}
+
+/// Composes a `.gitignore` from the named sets of rules, in order, on top of
+/// `existing` (what the file already holds, if anything). Each set is added
+/// under a `### Title` header, without the rules the file already has, and
+/// sets whose header is already there are left out altogether, so composing
+/// the same sets again changes nothing.
+pub fn compose(names: &[String], existing: &str) -> IoResult<String> {
+    let mut gitignores: Vec<&Gitignore> = Vec::new();
+    for name in names {
+        let gitignore = find(name).ok_or_else(|| {
+            let known: Vec<&str> = GITIGNORES.iter().map(|gitignore| gitignore.name).collect();
+            IoError::new(ErrorKind::InvalidInput, format!("unknown gitignore \"{}\" (known ones are {})", name, known.join(", ")))
+        })?;
+        if !gitignores.iter().any(|other| other.name == gitignore.name) {
+            gitignores.push(gitignore);
+        }
+    }
+
+    let mut content = existing.to_string();
+    for gitignore in gitignores {
+        let header = format!("### {}", gitignore.title);
+        if content.lines().any(|line| line.trim() == header) {
+            continue;
+        }
+
+        let blocks: Vec<String> = gitignore.text.split("\n\n").filter_map(|block| missing(block, &content)).collect();
+        if blocks.is_empty() {
+            continue;
+        }
+        if !content.is_empty() {
+            content.push_str(if content.ends_with('\n') { "\n" } else { "\n\n" });
+        }
+        content.push_str(&format!("{}\n\n{}\n", header, blocks.join("\n\n")));
+    }
+
+    return Ok(content);
+}
+
+// The lines of a block of rules (a comment and the rules it's about) that
+// aren't in the content yet, or nothing when every one of its rules is
+fn missing(block: &str, content: &str) -> Option<String> {
+    let is_rule = |line: &&str| !line.trim().is_empty() && !line.starts_with('#');
+    let lines: Vec<&str> = block.trim_end().lines().collect();
+    let kept: Vec<&str> = lines.iter()
+        .filter(|line| !is_rule(line) || !content.lines().any(|existing| existing.trim() == line.trim()))
+        .copied()
+        .collect();
+
+    // Blocks that are only comments (like a rule left commented out) are
+    // kept as they are
+    if lines.iter().any(is_rule) && !kept.iter().any(is_rule) {
+        return None;
+    }
+    return Some(kept.join("\n"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn composes_idempotently() {
+        let names = vec![String::from("rust"), String::from("vim"), String::from("Rust")];
+        let content = compose(&names, "").unwrap();
+        assert_eq!(content.matches("### ").count(), 2);
+        assert_eq!(compose(&names, &content).unwrap(), content);
+
+        // Rules the file already has aren't added again
+        let content = compose(&names, "target\n").unwrap();
+        assert!(content.starts_with("target\n\n### "));
+        assert_eq!(content.lines().filter(|line| line.trim() == "target" || line.trim() == "/target").count(), 1);
+        assert_eq!(compose(&names, &content).unwrap(), content);
+
+        assert_eq!(compose(&[String::from("nothing")], "").unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
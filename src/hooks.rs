@@ -0,0 +1,100 @@
+use heck::ToShoutySnakeCase;
+use log::debug;
+use std::{collections::HashMap, io::{Error as IoError, ErrorKind, Result as IoResult, Write}, path::{self, Path}, process::{Command, Stdio}, thread, time::{Duration, Instant}};
+
+// What hooks get to know about the project through their environment: every
+// answer as SRCINIT_VAR_<NAME> (e.g. SRCINIT_VAR_USE_NODE), and where the
+// project is (or will be) generated as SRCINIT_OUTPUT_DIR
+pub fn environment(variables: &HashMap<String, String>, output: &Path) -> Vec<(String, String)> {
+    let mut environment: Vec<(String, String)> = variables.iter()
+        .map(|(name, value)| (format!("SRCINIT_VAR_{}", name.to_shouty_snake_case()), value.clone()))
+        .collect();
+    let output = path::absolute(output).unwrap_or(output.to_path_buf());
+    environment.push((String::from("SRCINIT_OUTPUT_DIR"), output.to_string_lossy().to_string()));
+    return environment;
+}
+
+// Hooks are shell command lines, so they can use pipes, `&&` and the like
+pub fn command(hook: &str, dir: &Path) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(hook);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(hook);
+        command
+    };
+
+    command.current_dir(dir);
+    return command;
+}
+
+// Runs a hook in `dir`, with its output going straight to the terminal.
+// Anything given as input is written to its stdin, and a hook still running
+// once the timeout is over gets killed
+pub fn run(hook: &str, dir: &Path, environment: &[(String, String)], input: Option<&str>, timeout: Option<Duration>) -> IoResult<()> {
+    debug!("Running hook \"{}\" in \"{}\"", hook, dir.display());
+    let mut command = command(hook, dir);
+    command.envs(environment.iter().map(|(name, value)| (name, value)));
+    if input.is_some() {
+        command.stdin(Stdio::piped());
+    }
+
+    let mut child = command.spawn()?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        // Hooks are free to ignore their input, in which case they may well
+        // exit before it was all written, or never read it at all while they
+        // run. It's written on a thread of its own so the timeout still
+        // applies, and which ends once the hook does
+        let (hook, input) = (hook.to_string(), input.to_string());
+        thread::spawn(move || {
+            if let Err(error) = stdin.write_all(input.as_bytes()) {
+                debug!("Hook \"{}\" didn't read its input ({})", hook, error);
+            }
+        });
+    }
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        if timeout.is_some_and(|timeout| started.elapsed() >= timeout) {
+            child.kill()?;
+            child.wait()?;
+            return Err(IoError::new(ErrorKind::TimedOut, format!("hook \"{}\" timed out after {}s", hook, timeout.unwrap().as_secs())));
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    if !status.success() {
+        return Err(IoError::other(format!("hook \"{}\" failed ({})", hook, status)));
+    }
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn passes_input() {
+        let dir = std::env::temp_dir();
+        run("read answer && test \"$answer\" = yes", &dir, &[], Some("yes\n"), None).unwrap();
+        assert!(run("read answer && test \"$answer\" = yes", &dir, &[], Some("no\n"), None).is_err());
+    }
+
+    // More input than fits into a pipe, which the hook never reads
+    #[cfg(unix)]
+    #[test]
+    fn times_out_hooks_ignoring_their_input() {
+        let started = Instant::now();
+        let input = "x".repeat(1 << 20);
+        let error = run("sleep 30", &std::env::temp_dir(), &[], Some(&input), Some(Duration::from_secs(1))).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,44 @@
+#![allow(clippy::needless_return)]
+
+//! srcinit generates projects from templates, which come from the local
+//! template store or from sources (HTTP hosts, git repositories and local
+//! directories) synced into a cache.
+//!
+//! Most tools only need [`TemplateStore`] to look templates up and keep
+//! sources synced, and [`Generator`] to render a template into a directory.
+//! The modules below expose the lower-level building blocks the `srcinit`
+//! command itself is made of.
+
+pub mod built_info {
+    include!(concat!(env!("OUT_DIR"), "/built.rs"));
+}
+
+pub mod answers;
+pub mod archive;
+pub mod cargo_generate;
+pub mod config;
+pub mod cookiecutter;
+pub mod error;
+pub mod generator;
+pub mod gitignore;
+pub mod hooks;
+pub mod license;
+pub mod lint;
+pub mod lock;
+pub mod progress;
+pub mod publish;
+pub mod scaffold;
+pub mod serve;
+pub mod sources;
+pub mod store;
+pub mod template;
+pub mod testing;
+pub mod upgrade;
+pub mod vcs;
+pub mod watch;
+
+pub use error::SrcinitError;
+pub use generator::Generator;
+pub use lock::Lock;
+pub use store::TemplateStore;
+pub use template::Template;
@@ -0,0 +1,79 @@
+use std::{fs, io::Result as IoResult, path::{Path, PathBuf}};
+
+/// The file licenses are written to in generated projects.
+pub const FILE: &str = "LICENSE";
+
+/// A license srcinit can write without the template holding its text.
+pub struct License {
+    /// Its SPDX identifier, e.g. `MIT` or `Apache-2.0`.
+    pub id: &'static str,
+    pub name: &'static str,
+    text: &'static str,
+}
+
+// Texts mark where the year & copyright holder go with [year] and
+// [fullname]. The ones without a copyright line of their own (Apache, GPL...)
+// are written as they are
+pub const LICENSES: &[License] = &[
+    License { id: "0BSD", name: "BSD Zero Clause License", text: include_str!("licenses/0BSD.txt") },
+    License { id: "Apache-2.0", name: "Apache License 2.0", text: include_str!("licenses/Apache-2.0.txt") },
+    License { id: "BSD-2-Clause", name: "BSD 2-Clause \"Simplified\" License", text: include_str!("licenses/BSD-2-Clause.txt") },
+    License { id: "BSD-3-Clause", name: "BSD 3-Clause \"New\" or \"Revised\" License", text: include_str!("licenses/BSD-3-Clause.txt") },
+    License { id: "CC0-1.0", name: "Creative Commons Zero v1.0 Universal", text: include_str!("licenses/CC0-1.0.txt") },
+    License { id: "GPL-2.0-only", name: "GNU General Public License v2.0 only", text: include_str!("licenses/GPL-2.0.txt") },
+    License { id: "GPL-2.0-or-later", name: "GNU General Public License v2.0 or later", text: include_str!("licenses/GPL-2.0.txt") },
+    License { id: "GPL-3.0-only", name: "GNU General Public License v3.0 only", text: include_str!("licenses/GPL-3.0.txt") },
+    License { id: "GPL-3.0-or-later", name: "GNU General Public License v3.0 or later", text: include_str!("licenses/GPL-3.0.txt") },
+    License { id: "ISC", name: "ISC License", text: include_str!("licenses/ISC.txt") },
+    License { id: "LGPL-2.1-only", name: "GNU Lesser General Public License v2.1 only", text: include_str!("licenses/LGPL-2.1.txt") },
+    License { id: "LGPL-2.1-or-later", name: "GNU Lesser General Public License v2.1 or later", text: include_str!("licenses/LGPL-2.1.txt") },
+    License { id: "LGPL-3.0-only", name: "GNU Lesser General Public License v3.0 only", text: include_str!("licenses/LGPL-3.0.txt") },
+    License { id: "LGPL-3.0-or-later", name: "GNU Lesser General Public License v3.0 or later", text: include_str!("licenses/LGPL-3.0.txt") },
+    License { id: "MIT", name: "MIT License", text: include_str!("licenses/MIT.txt") },
+    License { id: "MPL-2.0", name: "Mozilla Public License 2.0", text: include_str!("licenses/MPL-2.0.txt") },
+    License { id: "Unlicense", name: "The Unlicense", text: include_str!("licenses/Unlicense.txt") },
+];
+
+/// Looks a license up by its SPDX identifier, ignoring case. The deprecated
+/// identifiers of the GNU licenses (`GPL-3.0`, `GPL-3.0+`...) are understood
+/// too.
+pub fn find(id: &str) -> Option<&'static License> {
+    let id = id.trim().to_ascii_uppercase();
+    let id = match id.strip_suffix('+') {
+        Some(id) => format!("{}-OR-LATER", id),
+        None if id.contains("GPL-") && !id.ends_with("-ONLY") && !id.ends_with("-OR-LATER") => format!("{}-ONLY", id),
+        None => id,
+    };
+    return LICENSES.iter().find(|license| license.id.eq_ignore_ascii_case(&id));
+}
+
+impl License {
+    /// The license's text, with the year and copyright holder filled in.
+    pub fn text(&self, year: &str, holder: &str) -> String {
+        return self.text.replace("[year]", year).replace("[fullname]", holder);
+    }
+
+    /// Writes the license into `dir` as [`FILE`], replacing whatever was
+    /// there. Returns where it was written.
+    pub fn write(&self, dir: &Path, year: &str, holder: &str) -> IoResult<PathBuf> {
+        let path = dir.join(FILE);
+        fs::write(&path, self.text(year, holder))?;
+        return Ok(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_licenses_by_alias() {
+        assert_eq!(find("mit").unwrap().id, "MIT");
+        assert_eq!(find(" Apache-2.0 ").unwrap().id, "Apache-2.0");
+        assert_eq!(find("GPL-3.0").unwrap().id, "GPL-3.0-only");
+        assert_eq!(find("GPL-3.0+").unwrap().id, "GPL-3.0-or-later");
+        assert_eq!(find("lgpl-2.1").unwrap().id, "LGPL-2.1-only");
+        assert_eq!(find("GPL-2.0-or-later").unwrap().id, "GPL-2.0-or-later");
+        assert!(find("MIT-0").is_none());
+    }
+}
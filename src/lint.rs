@@ -0,0 +1,325 @@
+use crate::{gitignore, license, template::{self, Filter, Ignore, Kind, Template, MANIFEST}};
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, error::Error, fmt, fs, io::Result as IoResult, path::Path};
+use tera::ast::{Expr, ExprVal, FunctionCall, Node};
+use walkdir::WalkDir;
+
+const MANIFEST_FIELDS: &[&str] = &[
+    "name", "description", "version", "author", "extends", "dependencies", "tags", "variables", "computed",
+    "pre_gen", "post_gen", "hook_timeout", "post_message", "license", "gitignore", "namespace", "syntax", "files", "modes", "tests",
+];
+const VARIABLE_FIELDS: &[&str] = &["name", "type", "default", "help", "choices", "secret", "ask_when", "pattern", "min", "max"];
+const FILE_FIELDS: &[&str] = &["path", "when"];
+const TEST_FIELDS: &[&str] = &["name", "values", "expected"];
+
+/// How bad a problem is: errors break generating (at least for some
+/// answers), warnings are most likely mistakes.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        return formatter.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        });
+    }
+}
+
+/// Something wrong with a template, found in the file at `location`
+/// (relative to the template).
+pub struct Problem {
+    pub severity: Severity,
+    pub location: String,
+    pub message: String,
+}
+
+impl Problem {
+    fn error(location: impl Into<String>, message: impl Into<String>) -> Problem {
+        return Problem { severity: Severity::Error, location: location.into(), message: message.into() };
+    }
+
+    fn warning(location: impl Into<String>, message: impl Into<String>) -> Problem {
+        return Problem { severity: Severity::Warning, location: location.into(), message: message.into() };
+    }
+}
+
+/// Checks a template for mistakes without generating anything: unknown or
+/// invalid manifest fields, variables used without being declared (or
+/// declared without being used), files that fail to parse or render to the
+/// same path, and text files that aren't UTF-8 and so would never be
+/// rendered.
+///
+/// `root` is the template's own directory, which for a template merged with
+/// others isn't the one it's rendered from.
+pub fn lint(template: &Template, root: &Path) -> IoResult<Vec<Problem>> {
+    let mut problems = Vec::new();
+    if let Ok(content) = fs::read_to_string(root.join(MANIFEST)) {
+        fields(&content, &mut problems);
+    }
+    manifest(template, &mut problems);
+
+    // Every name used by the files, their paths and the manifest's own
+    // expressions (along with where it's first used), apart from the ones
+    // templates set themselves (loop variables and the like)
+    let mut used: BTreeMap<String, String> = BTreeMap::new();
+    let mut bound = BTreeSet::from([String::from("loop")]);
+    let mut parse = |location: &str, content: &str, problems: &mut Vec<Problem>| match tera::Template::new(location, None, content) {
+        Ok(parsed) => {
+            let mut names_here = BTreeSet::new();
+            names(&parsed.ast, &mut names_here, &mut bound);
+            for name in names_here {
+                used.entry(name).or_insert_with(|| location.to_string());
+            }
+        }
+        Err(error) => problems.push(Problem::error(location, reason(&error))),
+    };
+
+    let manifest = &template.manifest;
+    for (name, value) in &manifest.computed {
+        if let Some(expression) = value.as_str() {
+            parse(&format!("{} (computed {})", MANIFEST, name), expression, &mut problems);
+        }
+    }
+    for rule in &manifest.files {
+        if let Some(when) = &rule.when {
+            parse(&format!("{} (condition for {})", MANIFEST, rule.path), &format!("{{% if {} %}}{{% endif %}}", when), &mut problems);
+        }
+    }
+    for variable in &manifest.variables {
+        if let Some(ask_when) = &variable.ask_when {
+            parse(&format!("{} (ask_when of {})", MANIFEST, variable.name), &format!("{{% if {} %}}{{% endif %}}", ask_when), &mut problems);
+        }
+    }
+    if let Some(message) = &manifest.post_message {
+        parse(&format!("{} (post_message)", MANIFEST), message, &mut problems);
+    }
+    if let Some(license) = &manifest.license {
+        parse(&format!("{} (license)", MANIFEST), license, &mut problems);
+    }
+    for name in &manifest.gitignore {
+        parse(&format!("{} (gitignore)", MANIFEST), name, &mut problems);
+    }
+
+    let ignore = Ignore::read(&template.path)?;
+    for entry in WalkDir::new(&template.path).min_depth(1).sort_by_file_name() {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(&template.path).unwrap();
+        let location = relative.to_string_lossy().to_string();
+        if relative == Path::new(MANIFEST) || ignore.matches(relative, entry.file_type().is_dir()) {
+            continue;
+        }
+
+        parse(&location, &relative.file_name().unwrap().to_string_lossy(), &mut problems);
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let content = fs::read(entry.path())?;
+        match String::from_utf8(content) {
+            Ok(content) if !content.contains('\0') => parse(&location, &content, &mut problems),
+            Ok(_) => {}
+            Err(error) if !error.as_bytes().iter().take(8000).any(|byte| *byte == 0) => {
+                problems.push(Problem::warning(&location, "not UTF-8 and without NUL bytes, so it's copied as binary without being rendered (convert it to UTF-8 if it's text)"));
+            }
+            Err(_) => {}
+        }
+    }
+
+    let known: BTreeSet<&str> = template::builtins().iter().map(|(name, _)| *name)
+        .chain(manifest.variables.iter().map(|variable| variable.name.as_str()))
+        .chain(manifest.computed.keys().map(String::as_str))
+        .chain(["project_name"])
+        .collect();
+    for (name, location) in used.iter().filter(|(name, _)| !known.contains(name.as_str()) && !bound.contains(*name)) {
+        problems.push(Problem::warning(location, format!("\"{}\" is used but never declared", name)));
+    }
+
+    // Hooks get the answers through their environment (or as JSON), so
+    // naming the variable anywhere counts as using it
+    let hooks: Vec<&String> = manifest.pre_gen.iter().chain(&manifest.post_gen).collect();
+    for variable in &manifest.variables {
+        if !used.contains_key(&variable.name) && !hooks.iter().any(|hook| hook.to_lowercase().contains(&variable.name.to_lowercase())) {
+            problems.push(Problem::warning(MANIFEST, format!("\"{}\" is declared but never used", variable.name)));
+        }
+    }
+
+    collisions(template, &mut problems);
+    return Ok(problems);
+}
+
+fn fields(content: &str, problems: &mut Vec<Problem>) {
+    let Ok(table) = content.parse::<toml::Table>() else {
+        return;
+    };
+
+    let unknown = |table: &toml::Table, known: &[&str], within: &str, problems: &mut Vec<Problem>| {
+        for key in table.keys().filter(|key| !known.contains(&key.as_str())) {
+            problems.push(Problem::warning(MANIFEST, format!("unknown field \"{}\"{}", key, within)));
+        }
+    };
+    unknown(&table, MANIFEST_FIELDS, "", problems);
+    for (field, known) in [("variables", VARIABLE_FIELDS), ("files", FILE_FIELDS), ("tests", TEST_FIELDS)] {
+        let entries = table.get(field).and_then(toml::Value::as_array).into_iter().flatten();
+        for entry in entries.filter_map(toml::Value::as_table) {
+            unknown(entry, known, &format!(" in {}", field), problems);
+        }
+    }
+}
+
+fn manifest(template: &Template, problems: &mut Vec<Problem>) {
+    let manifest = &template.manifest;
+    if let Some(version) = manifest.version.as_deref().filter(|version| template::parse_version(version).is_none()) {
+        problems.push(Problem::warning(MANIFEST, format!("version \"{}\" isn't a semantic version, so it can't be pinned or depended on", version)));
+    }
+    if let Err(error) = template.modes() {
+        problems.push(Problem::error(MANIFEST, error.to_string()));
+    }
+    if let Some(license) = manifest.license.as_deref().filter(|license| !license.contains("{{") && !license.contains("{%")) {
+        if !license.eq_ignore_ascii_case("none") && license::find(license).is_none() {
+            problems.push(Problem::error(MANIFEST, format!("unknown license \"{}\"", license)));
+        }
+    }
+    let names = manifest.gitignore.iter().filter(|name| !name.contains("{{") && !name.contains("{%")).flat_map(|name| name.split(','));
+    for name in names.filter(|name| !name.trim().is_empty() && gitignore::find(name).is_none()) {
+        problems.push(Problem::error(MANIFEST, format!("unknown gitignore \"{}\"", name.trim())));
+    }
+
+    for (index, variable) in manifest.variables.iter().enumerate() {
+        if manifest.variables[..index].iter().any(|other| other.name == variable.name) {
+            problems.push(Problem::error(MANIFEST, format!("\"{}\" is declared more than once", variable.name)));
+        }
+        if matches!(variable.kind, Kind::Choice | Kind::MultiChoice) && variable.choices.is_empty() {
+            problems.push(Problem::error(MANIFEST, format!("\"{}\" is a {} without any choices", variable.name, variable.kind.name())));
+        }
+        if let Some(Err(error)) = variable.default_value().map(|default| variable.validate(&default)) {
+            problems.push(Problem::error(MANIFEST, format!("default of \"{}\" is invalid: {}", variable.name, error)));
+        }
+    }
+
+    for rule in &manifest.files {
+        if !template.path.join(&rule.path).exists() {
+            problems.push(Problem::warning(MANIFEST, format!("rule for \"{}\" matches no file", rule.path)));
+        }
+    }
+}
+
+// Paths are rendered with the default answers (or a made-up one when there's
+// no default), so only collisions for those are found
+fn collisions(template: &Template, problems: &mut Vec<Problem>) {
+    let mut variables = template.variables(HashMap::from([(String::from("project_name"), template.name.clone())]));
+    for variable in &template.manifest.variables {
+        let sample = match variable.kind {
+            Kind::Bool => String::from("false"),
+            Kind::Int => variable.min.unwrap_or(0).to_string(),
+            Kind::Choice | Kind::MultiChoice => variable.choices.first().cloned().unwrap_or_default(),
+            Kind::String => variable.name.clone(),
+        };
+        variables.entry(variable.name.clone()).or_insert(sample);
+    }
+
+    let result = template.context(&variables)
+        .and_then(|context| template::plan(template, &mut template::engine(), &context, false, &Filter::default()));
+    if let Err(error) = result {
+        problems.push(Problem::error("", error.to_string()));
+    }
+}
+
+// Tera keeps the actual reason for a parse error in its sources
+fn reason(error: &tera::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    return message;
+}
+
+// Names of the variables a template uses (by the part before any `.` or
+// `[`), and the ones it sets itself
+fn names(nodes: &[Node], used: &mut BTreeSet<String>, bound: &mut BTreeSet<String>) {
+    for node in nodes {
+        match node {
+            Node::VariableBlock(_, expr) => expression(expr, used),
+            Node::Set(_, set) => {
+                bound.insert(set.key.clone());
+                expression(&set.value, used);
+            }
+            Node::MacroDefinition(_, definition, _) => {
+                bound.extend(definition.args.keys().cloned());
+                names(&definition.body, used, bound);
+            }
+            Node::FilterSection(_, section, _) => {
+                call(&section.filter, used);
+                names(&section.body, used, bound);
+            }
+            Node::Block(_, block, _) => names(&block.body, used, bound),
+            Node::Forloop(_, forloop, _) => {
+                bound.extend(forloop.key.clone());
+                bound.insert(forloop.value.clone());
+                expression(&forloop.container, used);
+                names(&forloop.body, used, bound);
+                names(forloop.empty_body.as_deref().unwrap_or_default(), used, bound);
+            }
+            Node::If(branches, _) => {
+                for (_, condition, body) in &branches.conditions {
+                    expression(condition, used);
+                    names(body, used, bound);
+                }
+                if let Some((_, body)) = &branches.otherwise {
+                    names(body, used, bound);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn expression(expr: &Expr, used: &mut BTreeSet<String>) {
+    for filter in &expr.filters {
+        call(filter, used);
+    }
+
+    match &expr.val {
+        ExprVal::Ident(ident) => identifier(ident, used),
+        ExprVal::Math(math) => {
+            expression(&math.lhs, used);
+            expression(&math.rhs, used);
+        }
+        ExprVal::Logic(logic) => {
+            expression(&logic.lhs, used);
+            expression(&logic.rhs, used);
+        }
+        ExprVal::In(within) => {
+            expression(&within.lhs, used);
+            expression(&within.rhs, used);
+        }
+        ExprVal::Test(test) => {
+            identifier(&test.ident, used);
+            test.args.iter().for_each(|arg| expression(arg, used));
+        }
+        ExprVal::MacroCall(call) => call.args.values().for_each(|arg| expression(arg, used)),
+        ExprVal::FunctionCall(function) => call(function, used),
+        ExprVal::Array(items) => items.iter().for_each(|item| expression(item, used)),
+        ExprVal::StringConcat(concat) => {
+            for value in &concat.values {
+                if let ExprVal::Ident(ident) = value {
+                    identifier(ident, used);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn call(function: &FunctionCall, used: &mut BTreeSet<String>) {
+    function.args.values().for_each(|arg| expression(arg, used));
+}
+
+fn identifier(ident: &str, used: &mut BTreeSet<String>) {
+    let name = ident.split(['.', '[']).next().unwrap_or(ident);
+    used.insert(name.to_string());
+}
@@ -0,0 +1,138 @@
+use crate::{sources, template::{self, Template}};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::{BTreeMap, HashMap}, fs, io::{Error as IoError, ErrorKind, Result as IoResult}, path::Path};
+
+pub const LOCKFILE: &str = ".srcinit.lock";
+
+/// Where a generated project came from, saved as `.srcinit.lock` in its root.
+///
+/// It records the template (and which version or commit of it), along with
+/// every variable answer, so the project can later be regenerated or
+/// upgraded to a newer version of its template.
+#[derive(Deserialize, Serialize)]
+pub struct Lock {
+    pub template: String,
+    pub source: String,
+    // Lockfiles written before origins were recorded tell it by their source
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<Origin>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    // Names the snapshot of the template taken when generating
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    // Add-on templates generated along with the template, see compose
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addons: Vec<String>,
+    pub srcinit: String,
+    pub generated: String,
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+/// Where the template of a project came from, which says how to get it
+/// again: `source` is the name of a source, or else the path or URL the
+/// template was given as.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Origin {
+    Source,
+    Path,
+    Git,
+    Archive,
+    Github,
+}
+
+impl Origin {
+    pub fn of(source: &str) -> Origin {
+        // Templates read in place are named after their directory as a
+        // file:// URL (unlike git ones, which come with a .git)
+        if source.starts_with("file://") && sources::git_remote(source).is_none() {
+            return Origin::Path;
+        } else if template::is_github(source) {
+            return Origin::Github;
+        } else if template::is_archive_url(source) {
+            return Origin::Archive;
+        } else if sources::git_remote(source).is_some() {
+            return Origin::Git;
+        }
+        return Origin::Source;
+    }
+}
+
+impl Lock {
+    /// Secret variables are left out of the lock
+    pub fn new(template: &Template, variables: &HashMap<String, String>) -> Lock {
+        let secrets: Vec<&String> = template.manifest.variables.iter()
+            .filter(|variable| variable.secret)
+            .map(|variable| &variable.name)
+            .collect();
+
+        return Lock {
+            template: template.name.clone(),
+            source: template.source.clone(),
+            origin: Some(Origin::of(&template.source)),
+            version: template.manifest.version.clone(),
+            commit: sources::revision(&template.source),
+            checksum: None,
+            addons: Vec::new(),
+            srcinit: env!("CARGO_PKG_VERSION").to_string(),
+            generated: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            variables: variables.iter().filter(|(name, _)| !secrets.contains(name)).map(|(name, value)| (name.clone(), value.clone())).collect(),
+        };
+    }
+
+    /// Reads the lockfile of a generated project.
+    pub fn read(project: &Path) -> IoResult<Lock> {
+        let content = fs::read_to_string(project.join(LOCKFILE))?;
+        return toml::from_str(&content)
+            .map_err(|error| IoError::new(ErrorKind::InvalidData, format!("invalid {}: {}", LOCKFILE, error.message())));
+    }
+
+    pub fn write(&self, project: &Path) -> IoResult<()> {
+        let content = toml::to_string(self).map_err(IoError::other)?;
+        return fs::write(project.join(LOCKFILE), content);
+    }
+
+    /// The template exactly as it was when generating, as long as its
+    /// snapshot is still in the cache.
+    pub fn snapshot(&self) -> Option<Template> {
+        let path = template::snapshot_path(self.checksum.as_deref()?);
+        if !path.is_dir() {
+            return None;
+        }
+
+        let mut template = Template::load(&self.source, path).ok()?;
+        template.name = self.template.clone();
+        return Some(template);
+    }
+
+    pub fn origin(&self) -> Origin {
+        return self.origin.unwrap_or_else(|| Origin::of(&self.source));
+    }
+
+    /// The template as "source/template", like [`Template::qualified_name`].
+    pub fn qualified_name(&self) -> String {
+        return format!("{}/{}", self.source, self.template);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_origins_apart() {
+        assert_eq!(Origin::of("local"), Origin::Source);
+        assert_eq!(Origin::of("corp"), Origin::Source);
+        assert_eq!(Origin::of("file:///home/me/template"), Origin::Path);
+        assert_eq!(Origin::of("file:///home/me/template.git"), Origin::Git);
+        assert_eq!(Origin::of("git+https://example.com/template"), Origin::Git);
+        assert_eq!(Origin::of("https://example.com/template.git"), Origin::Git);
+        assert_eq!(Origin::of("https://example.com/template.tar.gz"), Origin::Archive);
+        assert_eq!(Origin::of("gh:owner/repo/templates/cli"), Origin::Github);
+    }
+}
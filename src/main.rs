@@ -1,14 +1,92 @@
-pub mod built_info {
-    include!(concat!(env!("OUT_DIR"), "/built.rs"));
+#![allow(clippy::needless_return)]
+
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::{engine::{ArgValueCandidates, CompletionCandidate}, env::Shells, CompleteEnv, Shell};
+use clap_mangen::Man;
+use chrono::Local;
+use console::style;
+use dialoguer::{Confirm, FuzzySelect, Password};
+use env_logger::WriteStyle;
+use indicatif::HumanBytes;
+use log::LevelFilter;
+use serde_json::{json, Value};
+use srcinit::{answers, archive, config::Config, gitignore, hooks, license, lint::{self, Severity}, lock::{self, Lock, Origin}, publish, serve, sources::{self, remote::Change, Kind, Source, SourceStore, Synced}, template::{self, Conflict, Filter, Template}, testing, upgrade::Outcome, vcs, watch, Generator, SrcinitError, TemplateStore};
+use std::{collections::{BTreeMap, HashMap}, fs, io::{self, Error as IoError, ErrorKind, IsTerminal, Write}, net::TcpListener, path::{self, Path, PathBuf}, process, sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Mutex}, time::Duration};
+
+// Exit code of `sync --check` when templates have updates, see "Exit codes"
+// in README.md for the others
+const EXIT_OUTDATED: i32 = 6;
+
+// How often template-watch looks for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(300);
+
+// Whether informational output is silenced (--quiet). Errors, and whatever
+// a command was asked to show (lists, JSON...), are always printed
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+macro_rules! status {
+	($($arg:tt)*) => {
+		if !QUIET.load(Ordering::Relaxed) {
+			println!("{}", paint(Tone::Success, format!($($arg)*)));
+		}
+	};
+}
+
+macro_rules! notice {
+	($($arg:tt)*) => {
+		if !QUIET.load(Ordering::Relaxed) {
+			println!("{}", paint(Tone::Warning, format!($($arg)*)));
+		}
+	};
+}
+
+macro_rules! failure {
+	($($arg:tt)*) => {
+		eprintln!("{}", paint(Tone::Error, format!($($arg)*)))
+	};
+}
+
+#[derive(Clone, Copy)]
+enum Tone {
+	Success,
+	Warning,
+	Error,
+}
+
+// Colors the leading label of a message ("Synced", "Failed to sync"...),
+// leaving the details after it plain so they stay easy to read
+fn paint(tone: Tone, message: String) -> String {
+	let end = message.find(": ").or_else(|| message.find(" (")).unwrap_or(message.len());
+	let (label, details) = message.split_at(end);
+	let label = match tone {
+		Tone::Success => style(label).green(),
+		Tone::Warning => style(label).yellow(),
+		Tone::Error => style(label).red().bold().for_stderr(),
+	};
+	return format!("{}{}", label, details);
 }
 
-pub mod sources;
-pub mod template;
+#[derive(Clone, Copy, ValueEnum)]
+enum OnConflict {
+	Skip,
+	Overwrite,
+	Prompt,
+	Backup,
+}
 
-use clap::{Parser, Subcommand};
-use dialoguer::Confirm;
-use is_url::is_url;
-use std::{fs, path::PathBuf};
+#[derive(Clone, Copy, ValueEnum)]
+enum Bump {
+	Major,
+	Minor,
+	Patch,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorChoice {
+	Auto,
+	Always,
+	Never,
+}
 
 #[derive(Parser)]
 #[command(version)]
@@ -16,153 +94,1875 @@ use std::{fs, path::PathBuf};
 struct Cli {
 	#[command(subcommand)]
 	command: Option<Commands>,
-	#[arg(short, long, help = "Toggle verbose information")]
-	verbose: bool,
+	#[arg(short, long, global = true, action = ArgAction::Count, help = "Show more details, and debug (-v) or trace (-vv) logs")]
+	verbose: u8,
+	#[arg(short, long, global = true, conflicts_with = "verbose", help = "Only print errors & requested output, without progress")]
+	quiet: bool,
+	#[arg(long, global = true, help = "Never access the network, only use local & synced templates")]
+	offline: bool,
+	#[arg(long, global = true, help = "Print results & errors as JSON (one object per line)")]
+	json: bool,
+	#[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto, help = "When to color the output (auto also honors NO_COLOR)")]
+	color: ColorChoice,
+	#[arg(short, long, global = true, help = "Answer yes to confirmations & use defaults instead of prompting")]
+	yes: bool,
+}
+
+#[derive(Args)]
+struct HookFlags {
+	#[arg(long, help = "Run the template's hooks without asking")]
+	allow_hooks: bool,
+	#[arg(long, conflicts_with = "allow_hooks", help = "Never run the template's hooks")]
+	no_hooks: bool,
+	#[arg(long, value_name = "SECONDS", help = "Kill hooks running for longer than this (instead of the template's own timeout)")]
+	hook_timeout: Option<u64>,
+}
+
+#[derive(Args)]
+struct GitFlags {
+	#[arg(long, help = "Initialize a git repository with the generated files as first commit")]
+	git: bool,
+	#[arg(long, conflicts_with = "git", help = "Don't initialize a git repository, even if configured to")]
+	no_git: bool,
+	#[arg(long, value_name = "URL", conflicts_with = "no_git", help = "Add this remote as \"origin\" (implies --git)")]
+	git_remote: Option<String>,
+}
+
+#[derive(Args)]
+struct FilterFlags {
+	#[arg(long, value_name = "GLOB", help = "Only generate files whose path matches this glob (can be repeated)")]
+	only: Vec<String>,
+	#[arg(long, value_name = "GLOB", help = "Don't generate files whose path matches this glob (can be repeated)")]
+	exclude: Vec<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
 	#[command(about = "Generate source code using a template")]
 	Generate {
-		#[arg(help = "Template to use for generating source code")]
-		template: String,
+		#[arg(help = "Template to use for generating source code (optionally as source/template, picked from a list if omitted)", add = ArgValueCandidates::new(template_candidates))]
+		template: Option<String>,
+		#[arg(value_name = "+ADDON", value_parser = parse_addon, help = "Add-on templates to generate along with it, their files replacing the ones before them (e.g. +ci-github)")]
+		addons: Vec<String>,
 		#[arg(short, long, help = "Specify output directory")]
-		output: Option<String>
+		output: Option<String>,
+		#[arg(long, value_name = "FILE", conflicts_with = "on_conflict", help = "Pack the project into an archive (.tar.gz, .tgz or .zip) instead, named after the output directory inside")]
+		archive: Option<PathBuf>,
+		#[arg(short = 'D', long = "define", value_name = "KEY=VALUE", value_parser = parse_define, help = "Set a template variable (can be repeated)")]
+		defines: Vec<(String, String)>,
+		#[arg(long, value_name = "FILE", help = "Read template variables from a TOML or JSON file")]
+		values: Option<PathBuf>,
+		#[arg(long, value_name = "SPDX-ID", value_parser = parse_license, help = "Write a LICENSE file with this license (or none, for \"none\"), filled in with the year & author", add = ArgValueCandidates::new(license_candidates))]
+		license: Option<String>,
+		#[arg(long, value_enum, value_name = "POLICY", help = "Generate into a non-empty directory, handling existing files this way")]
+		on_conflict: Option<OnConflict>,
+		#[arg(long, help = "Only show the files & variables that would be used, without writing anything")]
+		dry_run: bool,
+		#[arg(long, help = "Don't offer the answers given to this template last time, nor remember these")]
+		no_remember: bool,
+		#[arg(long, help = "Generate what symlinks in the template point to, instead of the symlinks")]
+		follow_symlinks: bool,
+		#[command(flatten)]
+		filter: FilterFlags,
+		#[command(flatten)]
+		hooks: HookFlags,
+		#[command(flatten)]
+		git: GitFlags
 	},
 	#[command(about = "Sync other sources to latest changes")]
-	Sync {},
+	Sync {
+		#[arg(help = "Only sync this source", add = ArgValueCandidates::new(source_candidates))]
+		source: Option<String>,
+		#[arg(short, long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..), help = "Maximum number of sources synced at once")]
+		jobs: u16,
+		#[arg(long, default_value_t = 3, help = "How many times a failing download is retried")]
+		retries: u32,
+		#[arg(long, help = "Only report available updates, without downloading anything (exits with 6 if there are any)")]
+		check: bool
+	},
 	#[command(about = "List all templates from sources")]
 	List {
 		#[arg(short, long, help = "Only include templates from local source")]
 		local: bool
 	},
+	#[command(about = "Update a generated project to the latest version of its template")]
+	Upgrade {
+		#[arg(default_value = ".", help = "The directory of the generated project")]
+		path: PathBuf,
+		#[arg(short = 'D', long = "define", value_name = "KEY=VALUE", value_parser = parse_define, help = "Set a template variable (can be repeated)")]
+		defines: Vec<(String, String)>
+	},
+	#[command(about = "Add the files of a template to an existing project")]
+	Add {
+		#[arg(help = "Template to add (optionally as source/template)", add = ArgValueCandidates::new(template_candidates))]
+		template: String,
+		#[arg(default_value = ".", help = "The directory of the project")]
+		path: PathBuf,
+		#[arg(short = 'D', long = "define", value_name = "KEY=VALUE", value_parser = parse_define, help = "Set a template variable (can be repeated)")]
+		defines: Vec<(String, String)>,
+		#[arg(long, value_name = "FILE", help = "Read template variables from a TOML or JSON file")]
+		values: Option<PathBuf>,
+		#[arg(short, long, help = "Overwrite files the project already has")]
+		force: bool,
+		#[command(flatten)]
+		hooks: HookFlags
+	},
+	#[command(about = "Render a single file, writing the result to stdout")]
+	Render {
+		#[arg(help = "The file to be rendered (\"-\" for stdin)")]
+		file: Option<PathBuf>,
+		#[arg(long, conflicts_with = "file", help = "Read what to render from stdin")]
+		stdin: bool,
+		#[arg(short = 'D', long = "define", value_name = "KEY=VALUE", value_parser = parse_define, help = "Set a template variable (can be repeated)")]
+		defines: Vec<(String, String)>,
+		#[arg(long, value_name = "FILE", help = "Read template variables from a TOML or JSON file")]
+		values: Option<PathBuf>
+	},
+	#[command(about = "Compose a .gitignore from built-in sets of rules, writing it to stdout")]
+	Gitignore {
+		#[arg(value_name = "NAMES", value_delimiter = ',', required_unless_present = "list", help = "Sets of rules to compose, separated by commas (e.g. rust,node,macos)", add = ArgValueCandidates::new(gitignore_candidates))]
+		names: Vec<String>,
+		#[arg(short, long, value_name = "FILE", help = "Add the rules to this file instead, keeping what it already has")]
+		output: Option<PathBuf>,
+		#[arg(long, conflicts_with = "output", help = "List the sets of rules there are")]
+		list: bool
+	},
+	#[command(about = "Generate a project again in place, using its saved answers")]
+	Regenerate {
+		#[arg(default_value = ".", help = "The directory of the generated project")]
+		path: PathBuf,
+		#[arg(short = 'D', long = "define", value_name = "KEY=VALUE", value_parser = parse_define, help = "Override a saved answer (can be repeated)")]
+		defines: Vec<(String, String)>,
+		#[arg(long, value_enum, value_name = "POLICY", default_value = "skip", help = "How to handle files that already exist (by default, only missing files are restored)")]
+		on_conflict: OnConflict
+	},
+	#[command(about = "Show what a template contains & which variables it asks for")]
+	Info {
+		#[arg(help = "The name of the template (optionally as source/template)", add = ArgValueCandidates::new(template_candidates))]
+		template: String
+	},
+	#[command(about = "Search templates of all sources by name, description & tags")]
+	Search {
+		#[arg(help = "What to look for, matched fuzzily")]
+		query: String
+	},
+	#[command(about = "List all sources along with their status")]
+	SourceList {},
 	#[command(about = "Import local template from file")]
 	Import {
 		#[arg(help = "The file to be imported as a template")]
 		file: PathBuf
 	},
-	#[command(about = "Export template from local source to file")]
+	#[command(about = "Create a starter template to build a new one from")]
+	NewTemplate {
+		#[arg(help = "The name of the new template")]
+		name: String,
+		#[arg(short, long, help = "The directory to create the template in, instead of the local source")]
+		output: Option<PathBuf>
+	},
+	#[command(about = "Render a template with the answers of each of its tests")]
+	TemplateTest {
+		#[arg(help = "The name of the template to be tested (or a path to its directory)", add = ArgValueCandidates::new(template_candidates))]
+		template: String,
+		#[arg(long, help = "Replace the expected output of every test with what's rendered now")]
+		update: bool
+	},
+	#[command(about = "Check a template for mistakes without generating anything")]
+	TemplateLint {
+		#[arg(help = "The name of the template to be checked (or a path to its directory)", add = ArgValueCandidates::new(template_candidates))]
+		template: String
+	},
+	#[command(about = "Render a template into a preview directory whenever it changes")]
+	TemplateWatch {
+		#[arg(help = "The directory of the template to be watched")]
+		path: PathBuf,
+		#[arg(short, long, help = "The preview directory, cleared before every render")]
+		output: PathBuf,
+		#[arg(short = 'D', long = "define", value_name = "KEY=VALUE", value_parser = parse_define, help = "Set a variable (can be repeated)")]
+		defines: Vec<(String, String)>,
+		#[arg(long, value_name = "FILE", help = "Read variables from a TOML or JSON file")]
+		values: Option<PathBuf>
+	},
+	#[command(about = "Publish a template to a git, directory or HTTP source")]
+	Publish {
+		#[arg(help = "The name of the template to be published (or a path to its directory)", add = ArgValueCandidates::new(template_candidates))]
+		template: String,
+		#[arg(help = "The source to publish the template to", add = ArgValueCandidates::new(source_candidates))]
+		source: String,
+		#[arg(long, value_enum, value_name = "PART", help = "Increment this part of the template's version first")]
+		bump: Option<Bump>
+	},
+	#[command(about = "Serve the local templates to others as an HTTP source")]
+	Serve {
+		#[arg(long, default_value = "0.0.0.0:8080", help = "The address & port to listen on")]
+		address: String
+	},
+	#[command(about = "Export template from a source to file")]
 	Export {
-		#[arg(help = "The name of the selected template to be exported")]
+		#[arg(help = "The name of the selected template to be exported (optionally as source/template)", add = ArgValueCandidates::new(template_candidates))]
 		template: String,
 		#[arg(help = "The output directory where the template will be exported")]
 		output: PathBuf
 	},
 	#[command(about = "Remove existing template from local source")]
 	Remove {
-		#[arg(help = "The name of the selected template to be removed")]
-		template: String
+		#[arg(help = "The name of the selected template to be removed", add = ArgValueCandidates::new(local_template_candidates))]
+		template: String,
+		#[arg(short, long, help = "Forcefully perform operation")]
+		force: bool
 	},
 	#[command(about = "Add a new source")]
 	SourceAdd {
 		#[arg(help = "The name of the new source")]
 		source: String,
-		#[arg(help = "The URL of the new source")]
-		url: String
+		#[arg(help = "The URL (or local directory) of the new source")]
+		url: String,
+		#[arg(long, help = "Minisign public key used to verify the source index")]
+		public_key: Option<String>,
+		#[arg(long, value_name = "VARIABLE", help = "Environment variable holding a bearer token for the source")]
+		token_env: Option<String>,
+		#[arg(long = "mirror", value_name = "URL", help = "Fallback URL tried when the source is unavailable (can be repeated)")]
+		mirrors: Vec<String>,
+		#[arg(long, value_name = "URL", help = "Proxy used for this source instead of the environment (\"none\" to connect directly)")]
+		proxy: Option<String>,
+		#[arg(long, value_name = "FILE", help = "Additional CA certificate (PEM or DER) trusted for this source")]
+		ca_cert: Option<PathBuf>,
+		#[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), help = "How many versions of each template syncing keeps [default: 3]")]
+		keep_versions: Option<u32>,
+		#[arg(long, value_name = "URL", help = "Where publish uploads templates to (HTTP sources only)")]
+		upload_url: Option<String>
 	},
 	#[command(about = "Edit an existing source")]
 	SourceEdit {
-		#[arg(help = "The name of the existing source to be edited")]
+		#[arg(help = "The name of the existing source to be edited", add = ArgValueCandidates::new(source_candidates))]
 		source: String,
 		#[arg(help = "The new URL of the existing source")]
 		new_url: String
 	},
 	#[command(about = "Remove an existing source")]
 	SourceRemove {
-		#[arg(help = "The name of the existing source to be removed")]
+		#[arg(help = "The name of the existing source to be removed", add = ArgValueCandidates::new(source_candidates))]
+		source: String
+	},
+	#[command(about = "Rename an existing source, keeping its synced templates")]
+	SourceRename {
+		#[arg(help = "The current name of the existing source", add = ArgValueCandidates::new(source_candidates))]
+		source: String,
+		#[arg(help = "The new name of the source")]
+		new_name: String
+	},
+	#[command(about = "Sync a source and copy all of its templates into a directory, to be added as a source elsewhere")]
+	SourceMirror {
+		#[arg(help = "The name of the existing source to be mirrored", add = ArgValueCandidates::new(source_candidates))]
+		source: String,
+		#[arg(help = "The directory to copy the templates into (new or empty)")]
+		output: PathBuf
+	},
+	#[command(about = "Exclude a source from syncing, listing & generation")]
+	SourceDisable {
+		#[arg(help = "The name of the existing source to be disabled", add = ArgValueCandidates::new(source_candidates))]
+		source: String
+	},
+	#[command(about = "Include a previously disabled source again")]
+	SourceEnable {
+		#[arg(help = "The name of the existing source to be enabled", add = ArgValueCandidates::new(source_candidates))]
+		source: String
+	},
+	#[command(about = "Run hooks of a source's templates without asking first")]
+	SourceTrust {
+		#[arg(help = "The name of the existing source to be trusted", add = ArgValueCandidates::new(source_candidates))]
+		source: String
+	},
+	#[command(about = "Ask again before running hooks of a source's templates")]
+	SourceDistrust {
+		#[arg(help = "The name of the existing source to be distrusted", add = ArgValueCandidates::new(source_candidates))]
+		source: String
+	},
+	#[command(about = "Store a token for a source in the OS keyring")]
+	SourceLogin {
+		#[arg(help = "The name of the existing source to log in to", add = ArgValueCandidates::new(source_candidates))]
+		source: String
+	},
+	#[command(about = "Remove the token of a source from the OS keyring")]
+	SourceLogout {
+		#[arg(help = "The name of the existing source to log out of", add = ArgValueCandidates::new(source_candidates))]
 		source: String
 	},
 	#[command(about = "Remove all sources & delete everything")]
 	Reset {
 		#[arg(short, long, help = "Forcefully perform operation")]
 		force: bool
+	},
+	#[command(about = "Print the shell completion script (e.g. `source <(srcinit completions bash)`)")]
+	Completions {
+		#[arg(help = "The shell to print the completion script for")]
+		shell: Shell
+	},
+	#[command(about = "Print the man page, or write one per command into a directory", hide = true)]
+	Man {
+		#[arg(help = "Directory to write srcinit.1 & srcinit-<command>.1 into")]
+		output: Option<PathBuf>
 	}
 }
 
-fn main() {
-	// Parse command-line arguments using the clap library
-	// Also initialize the sources INI file (or if does not exist, create a new one)
-	let cli = Cli::parse();
+// Completion candidates are read from the store whenever the shell asks for
+// them, so newly synced templates & added sources complete right away
+fn source_candidates() -> Vec<CompletionCandidate> {
+	let Ok(store) = TemplateStore::open() else {
+		return Vec::new();
+	};
 
-	// Once parsed, we can use match statements to call different functions
-	// (e.g if "generate" is the subcommand, then we go to the generate block)
-	match &cli.command {
-		Some(Commands::SourceAdd { source, url }) => 'source_add: {
-			if !is_url(url) {
-				eprintln!("Failed to add new source: \"{}\" (URL malformed or invalid)", source);
-				break 'source_add;
+	return store.sources().iter()
+		.map(|source| CompletionCandidate::new(&source.name).help(Some(source.url.clone().into())))
+		.collect();
+}
+
+fn license_candidates() -> Vec<CompletionCandidate> {
+	return license::LICENSES.iter()
+		.map(|license| CompletionCandidate::new(license.id).help(Some(license.name.into())))
+		.collect();
+}
+
+fn gitignore_candidates() -> Vec<CompletionCandidate> {
+	return gitignore::GITIGNORES.iter()
+		.map(|gitignore| CompletionCandidate::new(gitignore.name).help(Some(gitignore.title.into())))
+		.collect();
+}
+
+fn template_candidates() -> Vec<CompletionCandidate> {
+	let Ok(store) = TemplateStore::open() else {
+		return Vec::new();
+	};
+
+	let mut candidates = Vec::new();
+	for source in store.sources().iter().filter(|source| source.enabled) {
+		for template in store.templates(&source.name) {
+			let help = template.manifest.description.clone().map(|description| description.into());
+			candidates.push(CompletionCandidate::new(template.qualified_name()).help(help));
+		}
+	}
+	return candidates;
+}
+
+fn local_template_candidates() -> Vec<CompletionCandidate> {
+	return template::list("local").into_iter()
+		.map(|template| CompletionCandidate::new(template.name))
+		.collect();
+}
+
+// Prints rows as a table, sizing each column to its widest cell
+fn print_table(indent: &str, header: &[&str], rows: Vec<Vec<String>>) {
+	let mut widths: Vec<usize> = header.iter().map(|cell| cell.len()).collect();
+	for row in &rows {
+		for (width, cell) in widths.iter_mut().zip(row) {
+			*width = (*width).max(cell.chars().count());
+		}
+	}
+
+	let last = header.len() - 1;
+	let line = |cells: Vec<String>| {
+		let padded: Vec<String> = cells.iter().enumerate()
+			.map(|(index, cell)| if index == last { cell.clone() } else { format!("{:<width$}", cell, width = widths[index]) })
+			.collect();
+		format!("{}{}", indent, padded.join("  "))
+	};
+
+	println!("{}", style(line(header.iter().map(|cell| cell.to_string()).collect())).dim());
+	for row in rows {
+		println!("{}", line(row));
+	}
+}
+
+// Lets the user search through every available template, showing
+// descriptions inline, and returns the qualified name of the chosen one
+fn pick(store: &TemplateStore) -> Result<String, SrcinitError> {
+	let templates: Vec<_> = store.sources().iter()
+		.filter(|source| source.enabled)
+		.flat_map(|source| store.templates(&source.name))
+		.collect();
+	if templates.is_empty() {
+		return Err(SrcinitError::TemplateNotFound(String::from("No templates available, import or sync some first")));
+	}
+
+	let items: Vec<String> = templates.iter().map(|template| match &template.manifest.description {
+		Some(description) => format!("{} - {}", template.qualified_name(), description),
+		None => template.qualified_name(),
+	}).collect();
+	let selected = FuzzySelect::new()
+		.with_prompt("Template")
+		.items(&items)
+		.default(0)
+		.interact_opt()?;
+
+	return match selected {
+		Some(index) => Ok(templates[index].qualified_name()),
+		None => Err(SrcinitError::Aborted),
+	};
+}
+
+fn parse_define(value: &str) -> Result<(String, String), String> {
+	let Some((key, value)) = value.split_once('=') else {
+		return Err(format!("expected KEY=VALUE, got \"{}\"", value));
+	};
+
+	return Ok((key.trim().to_string(), value.to_string()));
+}
+
+fn parse_addon(value: &str) -> Result<String, String> {
+	return match value.strip_prefix('+') {
+		Some(name) if !name.is_empty() => Ok(name.to_string()),
+		_ => Err(format!("expected +TEMPLATE, got \"{}\"", value)),
+	};
+}
+
+fn parse_license(value: &str) -> Result<String, String> {
+	if value.eq_ignore_ascii_case("none") || license::find(value).is_some() {
+		return Ok(value.to_string());
+	}
+
+	let ids: Vec<&str> = license::LICENSES.iter().map(|license| license.id).collect();
+	return Err(format!("unknown license \"{}\" (known ones are {})", value, ids.join(", ")));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn generate(cli: &Cli, name: &Option<String>, addons: &[String], output: &Option<String>, archive: &Option<PathBuf>, defines: &[(String, String)], values: &Option<PathBuf>, license: &Option<String>, on_conflict: Option<OnConflict>, dry_run: bool, remember: bool, follow_symlinks: bool, filter: &FilterFlags, flags: &HookFlags, git: &GitFlags) -> Result<(), SrcinitError> {
+	if archive.as_deref().is_some_and(|archive| !archive::is_archive(archive)) {
+		return Err(SrcinitError::validation("Archives have to end with .tar.gz, .tgz or .zip"));
+	}
+
+	let store = TemplateStore::open()?;
+	let name = match name {
+		Some(name) => name.clone(),
+		None if interactive(cli) => pick(&store)?,
+		None => return Err(SrcinitError::validation("No template given, and no terminal to pick one from")),
+	};
+
+	let mut scratch = None;
+	let template = resolve(cli, &store, &name, &mut scratch)?;
+	let addons = addons.iter().map(|name| find(&store, name)).collect::<Result<Vec<_>, _>>()?;
+	let addon_names: Vec<String> = addons.iter().map(Template::qualified_name).collect();
+	let template = template::compose(template, addons)?;
+
+	// Answers are remembered by source, which templates given as a URL or a
+	// path don't have
+	let remember = remember && !template.source.contains("://") && !template::is_github(&template.source);
+
+	// Without an explicit output directory, the project is generated
+	// into a new directory named after the template. Projects packed into
+	// an archive are generated into a scratch directory first
+	let output = PathBuf::from(output.clone().unwrap_or(template.name.clone()));
+	let packing = archive.as_ref().filter(|_| !dry_run).map(|_| Scratch::new());
+	let mut generator = match &packing {
+		Some(packing) => Generator::new(&template, packing.0.join(path::absolute(&output)?.file_name().unwrap_or_default())),
+		None => Generator::new(&template, &output),
+	};
+	let config = Config::load()?;
+	for (name, value) in &config.defaults {
+		generator.prefill(name, value);
+	}
+	// Answers given last time take precedence over the general defaults,
+	// they're specific to the template
+	if remember {
+		for (name, value) in answers::load(&template)? {
+			generator.prefill(&name, &value);
+		}
+	}
+
+	// Answers from a values file come first so that anything
+	// given with --define can still override them
+	if let Some(values) = values {
+		generator.values(values)?;
+	}
+	for (name, value) in defines {
+		generator.define(name, value);
+	}
+
+	answer(cli, &mut generator)?;
+	if follow_symlinks {
+		generator.follow_symlinks();
+	}
+	generator.filter(Filter::new(&filter.only, &filter.exclude)?);
+	if let Some(license) = license {
+		generator.license(license);
+	}
+
+	if dry_run {
+		return preview(cli, &generator);
+	}
+
+	let init = git.git_remote.is_some() || git.git || (!git.no_git && config.generate.git);
+	// Rendered up front, so a broken message doesn't surface only once the
+	// project is already generated
+	let message = generator.post_message()?;
+
+	if let Some(on_conflict) = on_conflict {
+		generator.on_conflict(policy(cli, on_conflict)?);
+	}
+
+	// Whoever is at the terminal gets to see what they're about to get
+	if interactive(cli) {
+		let files = generator.preview()?;
+		tree(generator.output(), &files);
+		if !confirm(cli, &format!("Generate {} files into \"{}\"?", files.len(), generator.output().display()))? {
+			return Err(SrcinitError::Aborted);
+		}
+	}
+
+	let run_hooks = allow_hooks(cli, &store, &template, flags)?;
+	let answers = serde_json::to_string(generator.variables()).unwrap();
+	let environment = hooks::environment(generator.variables(), generator.output());
+	let timeout = flags.hook_timeout.or(template.manifest.hook_timeout).map(Duration::from_secs);
+	for hook in template.manifest.pre_gen.iter().filter(|_| run_hooks) {
+		status!("Running: \"{}\"", hook);
+		hooks::run(hook, &template.path, &environment, Some(&answers), timeout)
+			.map_err(|error| SrcinitError::validation(format!("Answers were rejected, {}", error)))?;
+	}
+	generator.generate().map_err(|error| match error.kind() {
+		ErrorKind::AlreadyExists => SrcinitError::validation(format!("{}, pass --on-conflict to write into it", error)),
+		_ => error.into(),
+	})?;
+	// Upgrades have to compose the project the same way again
+	if !addon_names.is_empty() && filter.only.is_empty() && filter.exclude.is_empty() {
+		let mut lock = Lock::read(generator.output())?;
+		lock.addons = addon_names;
+		lock.write(generator.output())?;
+	}
+	if cli.json {
+		println!("{}", json!({ "template": template.qualified_name(), "output": archive.as_deref().unwrap_or(generator.output()), "message": message }));
+	} else {
+		status!("Generated: \"{}\" -> \"{}\"", template.qualified_name(), archive.as_deref().unwrap_or(generator.output()).display());
+	}
+	if remember {
+		if let Err(error) = answers::save(&template, generator.variables()) {
+			notice!("Not remembered: \"{}\" ({})", answers::path(&template).display(), error);
+		}
+	}
+
+	for hook in template.manifest.post_gen.iter().filter(|_| run_hooks) {
+		status!("Running: \"{}\"", hook);
+		hooks::run(hook, generator.output(), &environment, None, timeout)?;
+	}
+
+	// Hooks may well have changed files (e.g. formatting them), so the
+	// repository is only set up once they're done
+	if init && generator.output().join(".git").exists() {
+		notice!("Skipped git: \"{}\" (already a repository)", generator.output().display());
+	} else if init {
+		vcs::init(generator.output(), &format!("Generate from {}", template.qualified_name()), git.git_remote.as_deref())?;
+		status!("Initialized git: \"{}\"", generator.output().display());
+	}
+
+	// Packed last, so it holds whatever hooks & git added
+	if let Some(archive) = archive {
+		let root = generator.output().file_name().unwrap().to_string_lossy();
+		archive::pack_as(generator.output(), &root, archive)?;
+	}
+
+	if let Some(message) = message.filter(|_| !cli.json) {
+		println!();
+		println!("{}", message);
+	}
+	return Ok(());
+}
+
+// Variables that were already answered are never asked for, and without
+// prompts, every variable must at least have a default
+fn answer(cli: &Cli, generator: &mut Generator) -> Result<(), SrcinitError> {
+	if interactive(cli) {
+		generator.prompt()?;
+	} else if let Some(message) = missing(generator) {
+		return Err(SrcinitError::validation(message));
+	}
+
+	return Ok(generator.validate()?);
+}
+
+fn missing(generator: &Generator) -> Option<String> {
+	let missing = generator.missing();
+	if missing.is_empty() {
+		return None;
+	}
+
+	return Some(format!("Missing variables: {} (set them with --define)", missing.join(", ")));
+}
+
+// Hooks are arbitrary commands, which may well come from a remote source, so
+// they only run once the user agreed to it (or trusts the source)
+fn allow_hooks(cli: &Cli, store: &TemplateStore, template: &Template, flags: &HookFlags) -> Result<bool, SrcinitError> {
+	let manifest = &template.manifest;
+	if manifest.pre_gen.is_empty() && manifest.post_gen.is_empty() {
+		return Ok(false);
+	} else if flags.allow_hooks || (!flags.no_hooks && store.is_trusted(&template.source)) {
+		return Ok(true);
+	} else if flags.no_hooks {
+		notice!("Skipped hooks: \"{}\"", template.qualified_name());
+		return Ok(false);
+	} else if !interactive(cli) {
+		return Err(SrcinitError::validation("The template has hooks, pass --allow-hooks or --no-hooks to run without a terminal"));
+	}
+
+	eprintln!("\"{}\" wants to run:", template.qualified_name());
+	for hook in manifest.pre_gen.iter().chain(&manifest.post_gen) {
+		eprintln!("  {}", hook);
+	}
+	let allowed = Confirm::new().with_prompt("Run these hooks?").default(false).interact()?;
+	if !allowed {
+		notice!("Skipped hooks: \"{}\"", template.qualified_name());
+	}
+	return Ok(allowed);
+}
+
+fn policy(cli: &Cli, on_conflict: OnConflict) -> Result<Conflict, SrcinitError> {
+	return match on_conflict {
+		OnConflict::Prompt if !interactive(cli) => Err(SrcinitError::validation("Can't ask about conflicts without a terminal")),
+		OnConflict::Skip => Ok(Conflict::Skip),
+		OnConflict::Overwrite => Ok(Conflict::Overwrite),
+		OnConflict::Prompt => Ok(Conflict::Prompt),
+		OnConflict::Backup => Ok(Conflict::Backup),
+	};
+}
+
+fn preview(cli: &Cli, generator: &Generator) -> Result<(), SrcinitError> {
+	let files = generator.preview()?;
+	let redacted = generator.template().redact(generator.variables());
+	let mut variables: Vec<_> = redacted.iter().collect();
+	variables.sort();
+
+	if cli.json {
+		println!("{}", json!({
+			"template": generator.template().qualified_name(),
+			"output": generator.output(),
+			"dry_run": true,
+			"variables": redacted,
+			"files": files.iter().map(|(file, _)| file).collect::<Vec<_>>(),
+			"sizes": files.iter().map(|(_, size)| size).collect::<Vec<_>>(),
+			"pre_gen": generator.template().manifest.pre_gen,
+			"post_gen": generator.template().manifest.post_gen,
+		}));
+		return Ok(());
+	}
+
+	println!("Would generate: \"{}\" -> \"{}\"", generator.template().qualified_name(), generator.output().display());
+	println!("Variables:");
+	for (name, value) in variables {
+		println!("  {} = {:?}", name, value);
+	}
+	println!("Files:");
+	tree(generator.output(), &files);
+	let manifest = &generator.template().manifest;
+	if !manifest.pre_gen.is_empty() || !manifest.post_gen.is_empty() {
+		println!("Would run:");
+		for hook in manifest.pre_gen.iter().chain(&manifest.post_gen) {
+			println!("  {}", hook);
+		}
+	}
+	return Ok(());
+}
+
+// A directory of a tree, with its entries sorted by name. Only files have a
+// size
+#[derive(Default)]
+struct Tree {
+	entries: BTreeMap<String, Tree>,
+	size: Option<u64>,
+}
+
+// Prints files like `tree` does, under the directory they're generated into,
+// followed by how many there are and their total size
+fn tree(output: &Path, files: &[(PathBuf, u64)]) {
+	let mut root = Tree::default();
+	for (file, size) in files {
+		let mut node = &mut root;
+		for component in file {
+			node = node.entries.entry(component.to_string_lossy().to_string()).or_default();
+		}
+		node.size = Some(*size);
+	}
+
+	println!("{}/", style(output.display()).bold());
+	print_tree(&root, "");
+	let total: u64 = files.iter().map(|(_, size)| size).sum();
+	println!("{} files, {}", files.len(), HumanBytes(total));
+}
+
+fn print_tree(tree: &Tree, prefix: &str) {
+	for (index, (name, entry)) in tree.entries.iter().enumerate() {
+		let is_last = index == tree.entries.len() - 1;
+		let branch = if is_last { "└── " } else { "├── " };
+		match entry.size {
+			Some(size) => println!("{}{}{} ({})", prefix, branch, name, HumanBytes(size)),
+			None => {
+				println!("{}{}{}/", prefix, branch, style(name).bold());
+				print_tree(entry, &format!("{}{}", prefix, if is_last { "    " } else { "│   " }));
 			}
+		}
+	}
+}
+
+fn sync(cli: &Cli, only: &Option<String>, jobs: u16, retries: u32, check: bool) -> Result<(), SrcinitError> {
+	if cli.offline {
+		return Err(SrcinitError::validation("Offline mode is enabled"));
+	}
 
-			let mut sources = sources::init().unwrap_or(sources::new());
-			if sources.general_section().contains_key(source) {
-				eprintln!("Failed to add new source: \"{}\" (Already exists)", source);
-				break 'source_add;
+	let store = TemplateStore::open()?;
+	if only.as_deref().is_some_and(|only| only != "local" && !store.contains(only)) {
+		return Err(SrcinitError::Source(String::from("Does not exist")));
+	}
+	sources::remote::set_retries(retries);
+
+	// A source failing doesn't stop the others from syncing, but the
+	// command as a whole still fails afterwards
+	let failed = AtomicUsize::new(0);
+	if check {
+		let outdated = AtomicBool::new(false);
+		store.check(only.as_deref(), jobs as usize, |source, result| match result {
+			Ok(changes) if cli.json => {
+				outdated.fetch_or(!changes.is_empty(), Ordering::Relaxed);
+				let changes: Vec<Value> = changes.iter().map(|change| match change {
+					Change::Added { name, version } => json!({ "change": "added", "name": name, "version": version }),
+					Change::Updated { name, from, to } => json!({ "change": "updated", "name": name, "from": from, "to": to }),
+					Change::Removed { name } => json!({ "change": "removed", "name": name }),
+				}).collect();
+				println!("{}", json!({ "source": source, "changes": changes }));
+			}
+			Ok(changes) if changes.is_empty() => status!("Up to date: \"{}\"", source),
+			Ok(changes) => {
+				outdated.store(true, Ordering::Relaxed);
+				for change in changes {
+					match change {
+						Change::Added { name, version } => println!("{}", paint(Tone::Warning, format!("New template: \"{}/{}\" ({})", source, name, version.as_deref().unwrap_or("unversioned")))),
+						Change::Updated { name, from, to } => println!("{}", paint(Tone::Warning, format!("Update available: \"{}/{}\" ({} -> {})", source, name, from.as_deref().unwrap_or("unversioned"), to.as_deref().unwrap_or("unversioned")))),
+						Change::Removed { name } => println!("{}", paint(Tone::Warning, format!("Removed template: \"{}/{}\"", source, name))),
+					}
+				}
+			}
+			Err(error) => {
+				failed.fetch_add(1, Ordering::Relaxed);
+				if cli.json {
+					println!("{}", json!({ "source": source, "error": error.to_string() }));
+				} else {
+					failure!("Check failed: \"{}\" ({})", source, error);
+				}
 			}
-			
-			let mut sources_section = sources.with_general_section();
-			sources_section.add(source, url);
-			let result = sources::write(sources);
+		})?;
 
-			if let Err(..) = result {
-				eprintln!("An error occurred while trying to add a source");
+		let failed = failed.into_inner();
+		if failed == 0 && outdated.into_inner() {
+			process::exit(EXIT_OUTDATED);
+		}
+		return failures(failed);
+	}
+
+	let report = |source: &str, result: io::Result<Synced>| match result {
+		Ok(synced) if cli.json => {
+			println!("{}", json!({ "source": source, "templates": synced.templates, "url": synced.url, "updated": synced.updated }));
+		}
+		Ok(synced) => {
+			let status = if synced.updated { "" } else { ", up to date" };
+			if cli.verbose > 0 {
+				status!("Synced: \"{}\" ({} templates from {}{})", source, synced.templates, synced.url, status);
 			} else {
-				println!("Added new source: \"{}\" = \"{}\"", source, url);
+				status!("Synced: \"{}\" ({} templates{})", source, synced.templates, status);
 			}
 		}
-		Some(Commands::SourceEdit { source, new_url }) => 'source_edit: {
-			if !is_url(new_url) {
-				eprintln!("Failed to edit existing source: \"{}\" (New URL malformed or invalid)", source);
-				break 'source_edit;
+		Err(error) => {
+			failed.fetch_add(1, Ordering::Relaxed);
+			if cli.json {
+				println!("{}", json!({ "source": source, "error": error.to_string() }));
+			} else {
+				failure!("Sync failed: \"{}\" ({})", source, error);
 			}
+		}
+	};
+	store.sync(only.as_deref(), jobs as usize, report)?;
 
-			let mut sources = sources::init().unwrap_or(sources::new());
-			if !sources.general_section().contains_key(source) {
-				eprintln!("Failed to edit existing source: \"{}\" (Does not exist)", source);
-				break 'source_edit;
+	// Syncing a single source also syncs the sources its templates need
+	// templates from, as long as they're missing
+	if let Some(only) = only {
+		let mut synced = vec![only.clone()];
+		let mut pending = store.missing_sources(only);
+		while let Some(source) = pending.pop() {
+			if !synced.contains(&source) && store.is_enabled(&source) {
+				store.sync(Some(&source), jobs as usize, report)?;
+				pending.extend(store.missing_sources(&source));
+				synced.push(source);
 			}
+		}
+	}
+	return failures(failed.into_inner());
+}
+
+// Prompts can only be shown on a terminal, and --yes skips them entirely
+fn interactive(cli: &Cli) -> bool {
+	return !cli.yes && io::stdin().is_terminal();
+}
+
+// Asks for a confirmation, which --yes gives right away. Without a terminal
+// there's no one to ask, so it's an error rather than a silent yes
+fn confirm(cli: &Cli, prompt: &str) -> Result<bool, SrcinitError> {
+	if cli.yes {
+		return Ok(true);
+	}
+	if !interactive(cli) {
+		return Err(SrcinitError::validation("Confirmation needed, pass --yes to run without a terminal"));
+	}
+	return Ok(Confirm::new().with_prompt(prompt).interact()?);
+}
+
+fn failures(failed: usize) -> Result<(), SrcinitError> {
+	return match failed {
+		0 => Ok(()),
+		1 => Err(SrcinitError::Source(String::from("1 source failed"))),
+		failed => Err(SrcinitError::Source(format!("{} sources failed", failed))),
+	};
+}
+
+fn list(cli: &Cli, local: bool) -> Result<(), SrcinitError> {
+	let store = TemplateStore::open()?;
+	let mut listed = Vec::new();
+	for Source { name: source, url, enabled, .. } in store.sources().iter() {
+		if (local && source != "local") || !enabled {
+			continue;
+		}
+
+		if cli.json {
+			let templates: Vec<Value> = store.templates(source).iter().map(|template| json!({
+				"name": template.qualified_name(),
+				"description": template.manifest.description,
+				"version": template.manifest.version,
+				"versions": versions(template),
+			})).collect();
+			listed.push(json!({ "source": source, "url": url, "synced": store.is_cached(source), "templates": templates }));
+			continue;
+		}
+
+		println!("{} ({})", style(source).bold(), url);
+		if !store.is_cached(source) {
+			println!("  (not synced)");
+			continue;
+		}
+
+		let templates = store.templates(source);
+		if templates.is_empty() {
+			println!("  (no templates)");
+			continue;
+		}
+
+		let rows = templates.iter().map(|template| vec![
+			template.qualified_name(),
+			template.manifest.description.clone().unwrap_or(String::from("-")),
+			match versions(template).as_slice() {
+				[] => template.manifest.version.clone().unwrap_or(String::from("-")),
+				versions => versions.join(", "),
+			},
+		]).collect();
+		print_table("  ", &["NAME", "DESCRIPTION", "VERSIONS"], rows);
+	}
+
+	if cli.json {
+		println!("{}", Value::Array(listed));
+	}
+	return Ok(());
+}
+
+// A directory only needed while a command runs, removed however it ends
+struct Scratch(PathBuf);
+
+impl Scratch {
+	fn new() -> Scratch {
+		return Scratch(sources::cache_path().join(".scratch").join(format!("{:08x}", fastrand::u32(..))));
+	}
+}
 
-			let mut sources_section = sources.with_general_section();
-			sources_section.set(source, new_url);
-			let result = sources::write(sources);
+impl Drop for Scratch {
+	fn drop(&mut self) {
+		if self.0.exists() {
+			let _ = fs::remove_dir_all(&self.0);
+		}
+	}
+}
 
-			if let Err(..) = result {
-				eprintln!("An error occurred while trying to add a source");
+// Templates given as a git or archive URL (or a GitHub repository) are
+// fetched for this one project only, into a scratch directory that's removed
+// once the caller drops it
+fn resolve(cli: &Cli, store: &TemplateStore, name: &str, scratch: &mut Option<Scratch>) -> Result<Template, SrcinitError> {
+	let is_remote = sources::git_remote(name).is_some() || template::is_archive_url(name) || template::is_github(name);
+	let template = match name {
+		_ if is_remote && cli.offline => return Err(SrcinitError::validation("Offline mode is enabled")),
+		_ if is_remote => {
+			let path = scratch.insert(Scratch::new()).0.clone();
+			if template::is_github(name) {
+				template::download_github(name, &path).map_err(|error| match error.kind() {
+					ErrorKind::NotFound => SrcinitError::TemplateNotFound(error.to_string()),
+					_ => error.into(),
+				})?
+			} else if template::is_archive_url(name) {
+				template::download(name, &path)?
 			} else {
-				println!("Changed existing source: \"{}\" = \"{}\"", source, new_url);
+				template::fetch(name, &path)?
 			}
 		}
-		Some(Commands::Reset { force }) => 'reset: {
-			// If force isn't set or is set to false, we must confirm the user
-			// if they really want to wipe everything or not
-			if !force {
-				let confirmed = Confirm::new()
-										.with_prompt("Perform a reset operation?")
-										.interact()
-										.unwrap();
-				if !confirmed {
-					// abort operation if user said no
-					break 'reset;
-				}
+		_ if template::is_path(name) => template::open(Path::new(name)).map_err(|error| match error.kind() {
+			ErrorKind::NotFound => SrcinitError::TemplateNotFound(format!("\"{}\" does not exist", name)),
+			_ => error.into(),
+		})?,
+		_ => match store.find(name) {
+			Ok(template) => template,
+			Err(error) if cli.offline && error.kind() == ErrorKind::NotFound => {
+				return Err(SrcinitError::TemplateNotFound(format!("{}, and offline mode only uses local & synced templates", error)));
 			}
+			Err(error) if error.kind() == ErrorKind::NotFound => return Err(SrcinitError::TemplateNotFound(error.to_string())),
+			Err(error) => return Err(error.into()),
+		},
+	};
+	return Ok(template);
+}
 
-			// Locate application data directory
-			let dirs = vec![sources::dir_path()];
-			for directory in dirs {
-				if !directory.is_dir() {
-					println!("Skipped: \"{}\" (already wiped)", directory.display());
-					continue;
-				}
+fn find(store: &TemplateStore, name: &str) -> Result<Template, SrcinitError> {
+	return match store.find(name) {
+		Ok(template) => Ok(template),
+		Err(error) if error.kind() == ErrorKind::NotFound => Err(SrcinitError::TemplateNotFound(error.to_string())),
+		Err(error) => Err(error.into()),
+	};
+}
 
-				let result = fs::remove_dir_all(directory.clone());
-				if let Err(..) = result { 
-					eprintln!("Wipe failed: \"{}\" ({})", directory.display(), result.unwrap_err());
-				} else {
-					println!("Wiped: \"{}\"", directory.display());
+// The versions of a template that can be generated, newest first
+fn versions(template: &Template) -> Vec<String> {
+	return template::versions(template).iter().map(|version| version.to_string()).collect();
+}
+
+// The latest version of the template a project was generated from, composed
+// with the same add-ons. Templates that were given as a path or URL are read
+// from there again, since no source has them
+fn locked(cli: &Cli, store: &TemplateStore, lock: &Lock, scratch: &mut Option<Scratch>) -> Result<Template, SrcinitError> {
+	let name = match lock.origin() {
+		Origin::Source => lock.qualified_name(),
+		Origin::Path => lock.source.trim_start_matches("file://").to_string(),
+		Origin::Git | Origin::Archive | Origin::Github => lock.source.clone(),
+	};
+	let template = resolve(cli, store, &name, scratch)?;
+	let addons = lock.addons.iter().map(|name| find(store, name)).collect::<Result<Vec<_>, _>>()?;
+	return Ok(template::compose(template, addons)?);
+}
+
+fn read_lock(path: &Path) -> Result<Lock, SrcinitError> {
+	return Lock::read(path).map_err(|error| match error.kind() {
+		ErrorKind::NotFound => SrcinitError::validation(format!("No {} found, it wasn't generated by srcinit", lock::LOCKFILE)),
+		_ => error.into(),
+	});
+}
+
+// Add-on templates (a Dockerfile, CI workflows...) are meant to be generated
+// into a project that already exists, so they only ever add files to it
+fn add(cli: &Cli, name: &str, path: &Path, defines: &[(String, String)], values: &Option<PathBuf>, force: bool, flags: &HookFlags) -> Result<(), SrcinitError> {
+	if !path.is_dir() {
+		return Err(SrcinitError::validation(format!("\"{}\" isn't a directory", path.display())));
+	}
+
+	let store = TemplateStore::open()?;
+	let template = find(&store, name)?;
+	let mut generator = Generator::new(&template, path);
+	for (name, value) in &Config::load()?.defaults {
+		generator.prefill(name, value);
+	}
+	if let Some(values) = values {
+		generator.values(values)?;
+	}
+	for (name, value) in defines {
+		generator.define(name, value);
+	}
+	answer(cli, &mut generator)?;
+
+	// Nothing is written as long as any file would be clobbered
+	let existing: Vec<String> = generator.plan()?.into_iter()
+		.filter(|file| path.join(file).exists())
+		.map(|file| file.display().to_string())
+		.collect();
+	if !existing.is_empty() && !force {
+		return Err(SrcinitError::validation(format!("The project already has {} (pass --force to overwrite)", existing.join(", "))));
+	}
+	generator.on_conflict(if force { Conflict::Overwrite } else { Conflict::Skip });
+
+	let run_hooks = allow_hooks(cli, &store, &template, flags)?;
+	let answers = serde_json::to_string(generator.variables()).unwrap();
+	let environment = hooks::environment(generator.variables(), path);
+	let timeout = flags.hook_timeout.or(template.manifest.hook_timeout).map(Duration::from_secs);
+	for hook in template.manifest.pre_gen.iter().filter(|_| run_hooks) {
+		status!("Running: \"{}\"", hook);
+		hooks::run(hook, &template.path, &environment, Some(&answers), timeout)
+			.map_err(|error| SrcinitError::validation(format!("Answers were rejected, {}", error)))?;
+	}
+	generator.add()?;
+	if cli.json {
+		println!("{}", json!({ "template": template.qualified_name(), "output": path }));
+	} else {
+		status!("Added: \"{}\" -> \"{}\"", template.qualified_name(), path.display());
+	}
+
+	for hook in template.manifest.post_gen.iter().filter(|_| run_hooks) {
+		status!("Running: \"{}\"", hook);
+		hooks::run(hook, path, &environment, None, timeout)?;
+	}
+	return Ok(());
+}
+
+// srcinit's engine as a filter for shell pipelines, with only the built-ins
+// and the given variables to render with
+fn render(file: &Option<PathBuf>, stdin: bool, defines: &[(String, String)], values: &Option<PathBuf>) -> Result<(), SrcinitError> {
+	let content = match file {
+		Some(file) if file != Path::new("-") => fs::read_to_string(file)?,
+		None if !stdin => return Err(SrcinitError::validation("Nothing to render, give a file or pass --stdin")),
+		_ => io::read_to_string(io::stdin())?,
+	};
+
+	let mut variables = match values {
+		Some(values) => template::read_values(values)?,
+		None => HashMap::new(),
+	};
+	variables.extend(defines.iter().cloned());
+
+	let rendered = template::render_text(&content, &variables)?;
+	io::stdout().write_all(rendered.as_bytes())?;
+	return Ok(());
+}
+
+fn gitignore(cli: &Cli, names: &[String], output: &Option<PathBuf>, list: bool) -> Result<(), SrcinitError> {
+	if list {
+		if cli.json {
+			let gitignores: Vec<_> = gitignore::GITIGNORES.iter().map(|gitignore| json!({ "name": gitignore.name, "title": gitignore.title })).collect();
+			println!("{}", json!(gitignores));
+		} else {
+			for gitignore in gitignore::GITIGNORES {
+				println!("{} - {}", gitignore.name, gitignore.title);
+			}
+		}
+		return Ok(());
+	}
+
+	let Some(output) = output else {
+		io::stdout().write_all(gitignore::compose(names, "")?.as_bytes())?;
+		return Ok(());
+	};
+
+	let existing = match fs::read_to_string(output) {
+		Ok(existing) => existing,
+		Err(error) if error.kind() == ErrorKind::NotFound => String::new(),
+		Err(error) => return Err(error.into()),
+	};
+	let composed = gitignore::compose(names, &existing)?;
+	let changed = composed != existing;
+	if changed {
+		fs::write(output, composed)?;
+	}
+
+	if cli.json {
+		println!("{}", json!({ "output": output, "changed": changed }));
+	} else if changed {
+		status!("Composed: \"{}\" ({})", output.display(), names.join(", "));
+	} else {
+		status!("Unchanged: \"{}\" (it already has every rule)", output.display());
+	}
+	return Ok(());
+}
+
+fn regenerate(cli: &Cli, path: &Path, defines: &[(String, String)], on_conflict: OnConflict) -> Result<(), SrcinitError> {
+	let lock = read_lock(path)?;
+
+	// The same version of the template is used whenever it's still around,
+	// otherwise the project is regenerated from its current version
+	let store = TemplateStore::open()?;
+	let mut scratch = None;
+	let template = match lock.snapshot() {
+		Some(template) => template,
+		None => {
+			notice!("Outdated: \"{}\" (the version the project was generated from is gone, using the latest)", lock.qualified_name());
+			locked(cli, &store, &lock, &mut scratch)?
+		}
+	};
+
+	let mut generator = Generator::new(&template, path);
+	for (name, value) in &lock.variables {
+		generator.define(name, value);
+	}
+	for (name, value) in defines {
+		generator.define(name, value);
+	}
+	// Secrets aren't in the lock, so they're asked for again
+	answer(cli, &mut generator)?;
+
+	generator.on_conflict(policy(cli, on_conflict)?);
+	generator.generate()?;
+
+	// A snapshot doesn't know which commit it came from or which add-ons it
+	// was composed of, the old lock does
+	let mut regenerated = Lock::read(path)?;
+	if lock.snapshot().is_some() {
+		regenerated.commit = lock.commit;
+	}
+	regenerated.addons = lock.addons;
+	regenerated.write(path)?;
+
+	if cli.json {
+		println!("{}", json!({ "template": template.qualified_name(), "output": path }));
+	} else {
+		status!("Regenerated: \"{}\" -> \"{}\"", template.qualified_name(), path.display());
+	}
+	return Ok(());
+}
+
+fn upgrade(cli: &Cli, path: &Path, defines: &[(String, String)]) -> Result<(), SrcinitError> {
+	let lock = read_lock(path)?;
+	let store = TemplateStore::open()?;
+	let mut scratch = None;
+	let template = locked(cli, &store, &lock, &mut scratch)?;
+
+	// Saved answers are reused, so only variables the template gained since
+	// are asked for (or overridden with --define)
+	let mut generator = Generator::new(&template, path);
+	for (name, value) in &lock.variables {
+		generator.define(name, value);
+	}
+	for (name, value) in defines {
+		generator.define(name, value);
+	}
+	answer(cli, &mut generator)?;
+
+	let outcomes = generator.upgrade()?;
+	if !lock.addons.is_empty() {
+		let mut upgraded = Lock::read(path)?;
+		upgraded.addons = lock.addons.clone();
+		upgraded.write(path)?;
+	}
+	let conflicts = outcomes.iter().filter(|(_, outcome)| *outcome == Outcome::Conflict).count();
+	let from = lock.version.as_deref().unwrap_or("unversioned");
+	let to = template.manifest.version.as_deref().unwrap_or("unversioned");
+	if cli.json {
+		let files: Vec<Value> = outcomes.iter().map(|(file, outcome)| json!({
+			"path": file,
+			"outcome": format!("{:?}", outcome).to_lowercase(),
+		})).collect();
+		println!("{}", json!({ "template": template.qualified_name(), "from": from, "to": to, "files": files }));
+	} else {
+		for (file, outcome) in &outcomes {
+			match outcome {
+				Outcome::Added => status!("Added: \"{}\"", file.display()),
+				Outcome::Updated => status!("Updated: \"{}\"", file.display()),
+				Outcome::Removed => status!("Removed: \"{}\"", file.display()),
+				Outcome::Merged => status!("Merged: \"{}\"", file.display()),
+				Outcome::Conflict => notice!("Conflict: \"{}\"", file.display()),
+			}
+		}
+	}
+
+	match conflicts {
+		0 => {}
+		1 => return Err(SrcinitError::validation("1 file has conflicts left to resolve")),
+		conflicts => return Err(SrcinitError::validation(format!("{} files have conflicts left to resolve", conflicts))),
+	}
+	if !cli.json {
+		status!("Upgraded: \"{}\" ({} -> {})", template.qualified_name(), from, to);
+	}
+	return Ok(());
+}
+
+fn info(cli: &Cli, name: &str) -> Result<(), SrcinitError> {
+	let store = TemplateStore::open()?;
+	let template = find(&store, name)?;
+	let manifest = &template.manifest;
+	let (files, size) = template.footprint();
+
+	if cli.json {
+		// Defaults of secrets are left out, like they're masked below
+		let mut variables = manifest.variables.clone();
+		for variable in variables.iter_mut().filter(|variable| variable.secret) {
+			variable.default = None;
+		}
+		println!("{}", json!({
+			"name": template.qualified_name(),
+			"source": template.source,
+			"path": template.path,
+			"description": manifest.description,
+			"version": manifest.version,
+			"versions": versions(&template),
+			"author": manifest.author,
+			"tags": manifest.tags,
+			"files": files,
+			"size": size,
+			"variables": variables,
+			"pre_gen": manifest.pre_gen,
+			"post_gen": manifest.post_gen,
+		}));
+		return Ok(());
+	}
+
+	println!("{} ({})", style(template.qualified_name()).bold(), manifest.version.as_deref().unwrap_or("unversioned"));
+	if let Some(description) = &manifest.description {
+		println!("{}", description);
+	}
+	println!();
+	if let Some(author) = &manifest.author {
+		println!("Author: {}", author);
+	}
+	if !manifest.tags.is_empty() {
+		println!("Tags: {}", manifest.tags.join(", "));
+	}
+	let available = versions(&template);
+	if available.len() > 1 {
+		println!("Versions: {} (generate one as {}@<version>)", available.join(", "), template.qualified_name());
+	}
+	println!("Source: {}", template.source);
+	println!("Path: {}", template.path.display());
+	println!("Files: {} ({})", files, HumanBytes(size));
+	if !manifest.pre_gen.is_empty() {
+		println!("Runs before generating:");
+		for hook in &manifest.pre_gen {
+			println!("  {}", hook);
+		}
+	}
+	if !manifest.post_gen.is_empty() {
+		println!("Runs after generating:");
+		for hook in &manifest.post_gen {
+			println!("  {}", hook);
+		}
+	}
+
+	if manifest.variables.is_empty() {
+		println!("Variables: none");
+		return Ok(());
+	}
+
+	println!("Variables:");
+	let rows = manifest.variables.iter().map(|variable| vec![
+		variable.name.clone(),
+		if variable.choices.is_empty() { variable.kind.name().to_string() } else { format!("{} ({})", variable.kind.name(), variable.choices.join(", ")) },
+		if variable.secret { String::from("********") } else { variable.default_value().unwrap_or(String::from("-")) },
+		variable.help.clone().unwrap_or(String::from("-")),
+	]).collect();
+	print_table("  ", &["NAME", "TYPE", "DEFAULT", "HELP"], rows);
+	return Ok(());
+}
+
+fn search(cli: &Cli, query: &str) -> Result<(), SrcinitError> {
+	let store = TemplateStore::open()?;
+	let found = store.search(query);
+	if found.is_empty() {
+		return Err(SrcinitError::TemplateNotFound(String::from("No templates match")));
+	}
+
+	if cli.json {
+		let found: Vec<Value> = found.iter().map(|template| json!({
+			"name": template.qualified_name(),
+			"description": template.manifest.description,
+			"version": template.manifest.version,
+			"tags": template.manifest.tags,
+		})).collect();
+		println!("{}", Value::Array(found));
+		return Ok(());
+	}
+
+	let rows = found.iter().map(|template| vec![
+		template.qualified_name(),
+		template.manifest.description.clone().unwrap_or(String::from("-")),
+		if template.manifest.tags.is_empty() { String::from("-") } else { template.manifest.tags.join(", ") },
+	]).collect();
+	print_table("", &["NAME", "DESCRIPTION", "TAGS"], rows);
+	return Ok(());
+}
+
+fn source_list(cli: &Cli) -> Result<(), SrcinitError> {
+	let store = TemplateStore::open()?;
+	if cli.json {
+		let listed: Vec<Value> = store.sources().iter().map(|source| {
+			let cached = store.is_cached(&source.name);
+			json!({
+				"name": source.name,
+				"url": source.url,
+				"kind": source.kind,
+				"enabled": source.enabled,
+				"last_synced": source.last_synced().map(|time| time.to_rfc3339()),
+				"templates": if cached { Some(store.templates(&source.name).len()) } else { None },
+				"cached": cached,
+			})
+		}).collect();
+		println!("{}", Value::Array(listed));
+		return Ok(());
+	}
+
+	let rows = store.sources().iter().map(|source| {
+		let cached = source.templates_path().is_dir();
+		let last_synced = source.last_synced()
+			.map(|time| time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string());
+		vec![
+			source.name.clone(),
+			source.url.clone(),
+			String::from(if source.enabled { "yes" } else { "no" }),
+			last_synced.unwrap_or(String::from("-")),
+			if cached { store.templates(&source.name).len().to_string() } else { String::from("-") },
+			String::from(if cached { "yes" } else { "no" }),
+		]
+	}).collect();
+	print_table("", &["NAME", "URL", "ENABLED", "LAST SYNC", "TEMPLATES", "CACHED"], rows);
+	return Ok(());
+}
+
+fn import(file: &Path) -> Result<(), SrcinitError> {
+	let template = TemplateStore::open()?.import(file)?;
+	status!("Imported: \"{}\" -> \"{}\"", template.name, sources::templates_path("local").join(&template.name).display());
+	return Ok(());
+}
+
+fn new_template(name: &str, output: &Option<PathBuf>) -> Result<(), SrcinitError> {
+	let path = TemplateStore::open()?.scaffold(name, output.as_deref())?;
+	status!("Created template: \"{}\" -> \"{}\"", name, path.display());
+	return Ok(());
+}
+
+// Templates merged with the ones they extend or depend on are rendered from
+// the cache, so where they actually are comes along with them
+fn authored(name: &str) -> Result<(Template, PathBuf), SrcinitError> {
+	if template::is_path(name) {
+		return Ok((template::open(Path::new(name))?, fs::canonicalize(name)?));
+	}
+
+	let template = find(&TemplateStore::open()?, name)?;
+	let root = sources::templates_path(&template.source).join(&template.name);
+	return Ok((template, root));
+}
+
+fn template_lint(cli: &Cli, name: &str) -> Result<(), SrcinitError> {
+	let (template, root) = authored(name)?;
+	let problems = lint::lint(&template, &root)?;
+	if cli.json {
+		let problems: Vec<Value> = problems.iter().map(|problem| json!({
+			"severity": problem.severity.to_string(),
+			"location": problem.location,
+			"message": problem.message,
+		})).collect();
+		println!("{}", Value::Array(problems));
+	} else {
+		for problem in &problems {
+			let location = if problem.location.is_empty() { String::new() } else { format!("{}: ", problem.location) };
+			match problem.severity {
+				Severity::Error => failure!("Error: {}{}", location, problem.message),
+				Severity::Warning => notice!("Warning: {}{}", location, problem.message),
+			}
+		}
+	}
+
+	let errors = problems.iter().filter(|problem| problem.severity == Severity::Error).count();
+	let warnings = problems.len() - errors;
+	if errors > 0 {
+		return Err(SrcinitError::validation(format!("{} errors, {} warnings", errors, warnings)));
+	} else if !cli.json {
+		status!("Linted: \"{}\" ({} warnings)", template.qualified_name(), warnings);
+	}
+	return Ok(());
+}
+
+fn template_watch(path: &Path, output: &Path, defines: &[(String, String)], values: &Option<PathBuf>) -> Result<(), SrcinitError> {
+	let source = template::open(path)?.source;
+
+	// The preview is cleared before every render, so it has to be a directory
+	// of its own (or one that was a preview of the same template before)
+	let is_preview = Lock::read(output).is_ok_and(|lock| lock.source == source);
+	if output.read_dir().is_ok_and(|mut entries| entries.next().is_some()) && !is_preview {
+		return Err(SrcinitError::validation(format!("\"{}\" isn't empty, the preview needs a directory of its own", output.display())));
+	}
+
+	let mut watched = vec![fs::canonicalize(path)?];
+	if std::path::absolute(output)?.starts_with(&watched[0]) {
+		return Err(SrcinitError::validation("The preview can't be inside the template, every render would change it"));
+	}
+	watched.extend(values.clone());
+	status!("Watching: \"{}\" (Ctrl+C to stop)", path.display());
+	watch::watch(&watched, WATCH_INTERVAL, || match render_preview(path, output, defines, values) {
+		Ok(()) => status!("Rendered: \"{}\" -> \"{}\" ({})", path.display(), output.display(), Local::now().format("%H:%M:%S")),
+		Err(error) => failure!("Failed to render: \"{}\" ({})", path.display(), error),
+	});
+}
+
+// Hooks never run for previews, they'd run on every change
+fn render_preview(path: &Path, output: &Path, defines: &[(String, String)], values: &Option<PathBuf>) -> Result<(), SrcinitError> {
+	let template = template::open(path)?;
+	let mut generator = Generator::new(&template, output);
+	if let Some(values) = values {
+		generator.values(values)?;
+	}
+	for (name, value) in defines {
+		generator.define(name, value);
+	}
+	if let Some(message) = missing(&generator) {
+		return Err(SrcinitError::validation(message));
+	}
+
+	if output.exists() {
+		fs::remove_dir_all(output)?;
+	}
+	return Ok(generator.generate()?);
+}
+
+fn template_test(cli: &Cli, name: &str, update: bool) -> Result<(), SrcinitError> {
+	let (template, root) = authored(name)?;
+	let tests = &template.manifest.tests;
+	if tests.is_empty() {
+		return Err(SrcinitError::validation("The template has no tests"));
+	}
+
+	let mut results = Vec::new();
+	let mut failed = 0;
+	for test in tests {
+		let result = testing::run(&template, &root, test, update);
+		if !result.as_ref().is_ok_and(Vec::is_empty) {
+			failed += 1;
+		}
+
+		if cli.json {
+			results.push(match result {
+				Ok(differences) => json!({
+					"name": test.name(),
+					"passed": differences.is_empty(),
+					"differences": differences.iter().map(|difference| json!({ "kind": difference.kind(), "path": difference.path() })).collect::<Vec<_>>(),
+				}),
+				Err(error) => json!({ "name": test.name(), "passed": false, "error": error.to_string() }),
+			});
+			continue;
+		}
+
+		match result {
+			Ok(_) if update && test.expected.is_some() => status!("Updated: \"{}\"", test.name()),
+			Ok(differences) if differences.is_empty() => status!("Passed: \"{}\"", test.name()),
+			Ok(differences) => {
+				notice!("Failed: \"{}\" (output differs from \"{}\")", test.name(), test.expected.as_deref().unwrap_or_default());
+				for difference in differences {
+					println!("  {}: {}", difference.kind(), difference.path().display());
 				}
 			}
+			Err(error) => notice!("Failed: \"{}\" ({})", test.name(), error),
+		}
+	}
+
+	if cli.json {
+		println!("{}", Value::Array(results));
+	}
+	return match failed {
+		0 => Ok(()),
+		failed => Err(SrcinitError::validation(format!("{} of {} tests failed", failed, tests.len()))),
+	};
+}
+
+fn publish(cli: &Cli, name: &str, source: &str, bump: Option<Bump>) -> Result<(), SrcinitError> {
+	let store = TemplateStore::open()?;
+	let Some(Source { kind, .. }) = store.sources().get(source) else {
+		return Err(SrcinitError::Source(format!("Source \"{}\" does not exist", source)));
+	};
+	if cli.offline && *kind != Kind::Directory {
+		return Err(SrcinitError::validation("Offline mode is enabled"));
+	}
+
+	let (_, root) = authored(name)?;
+	if let Some(bump) = bump {
+		let level = match bump {
+			Bump::Major => publish::Level::Major,
+			Bump::Minor => publish::Level::Minor,
+			Bump::Patch => publish::Level::Patch,
+		};
+		let version = publish::bump(&root, level)?;
+		status!("Bumped: \"{}\" -> {}", name, version);
+	}
+
+	let version = store.publish(&root, source)?;
+	if cli.json {
+		println!("{}", json!({ "template": name, "source": source, "version": version.to_string() }));
+	} else {
+		status!("Published: \"{}\" {} -> \"{}\"", name, version, source);
+	}
+	return Ok(());
+}
+
+fn serve(address: &str) -> Result<(), SrcinitError> {
+	let listener = TcpListener::bind(address)?;
+	let address = listener.local_addr()?;
+	status!("Serving: \"local\" on http://{} (Ctrl+C to stop)", address);
+	return Ok(serve::serve(listener, "local")?);
+}
+
+fn export(template: &str, output: &Path) -> Result<(), SrcinitError> {
+	let store = TemplateStore::open()?;
+	let file = match store.export(template, output) {
+		Err(error) if error.kind() == ErrorKind::NotFound => return Err(SrcinitError::TemplateNotFound(error.to_string())),
+		result => result?,
+	};
+	status!("Exported: \"{}\" -> \"{}\"", template, file.display());
+	return Ok(());
+}
+
+fn remove(cli: &Cli, template: &str, force: bool) -> Result<(), SrcinitError> {
+	// Anything else (like "..") would point outside of the local store
+	if !template::is_valid_name(template) {
+		return Err(SrcinitError::validation(format!("Invalid template name \"{}\"", template)));
+	}
+
+	let path = sources::templates_path("local").join(template);
+	if !path.is_dir() {
+		return Err(SrcinitError::TemplateNotFound(String::from("Does not exist")));
+	}
+
+	if !force && !confirm(cli, &format!("Remove template \"{}\"?", template))? {
+		return Err(SrcinitError::Aborted);
+	}
+
+	fs::remove_dir_all(&path)?;
+	status!("Removed template: \"{}\"", template);
+	return Ok(());
+}
+
+#[allow(clippy::too_many_arguments)]
+fn source_add(source: &str, url: &str, public_key: &Option<String>, token_env: &Option<String>, mirrors: &[String], proxy: &Option<String>, ca_cert: &Option<PathBuf>, keep_versions: Option<u32>, upload_url: &Option<String>) -> Result<(), SrcinitError> {
+	let mut sources = SourceStore::load()?;
+	let mut new = Source::new(source, url);
+	new.auth = token_env.as_ref().map(|variable| format!("env:{}", variable));
+	new.mirrors = mirrors.to_vec();
+	new.public_key = public_key.clone();
+	new.proxy = proxy.clone();
+	new.ca_cert = ca_cert.as_ref().map(|ca_cert| ca_cert.to_string_lossy().to_string());
+	new.keep_versions = keep_versions.map(|keep| keep as usize);
+	new.upload_url = upload_url.clone();
+
+	let url = sources.add(new)?.url.clone();
+	sources.save()?;
+	status!("Added new source: \"{}\" = \"{}\"", source, url);
+	return Ok(());
+}
+
+// Sources that don't exist are reported as such rather than as IO errors
+fn source_error(error: IoError) -> SrcinitError {
+	return match error.kind() {
+		ErrorKind::NotFound => SrcinitError::Source(error.to_string()),
+		_ => SrcinitError::from(error),
+	};
+}
+
+fn source_edit(source: &str, new_url: &str) -> Result<(), SrcinitError> {
+	let mut sources = SourceStore::load()?;
+	let new_url = sources.edit(source, new_url).map_err(source_error)?.url.clone();
+	sources.save()?;
+
+	status!("Changed existing source: \"{}\" = \"{}\"", source, new_url);
+	return Ok(());
+}
+
+fn source_remove(cli: &Cli, source: &str) -> Result<(), SrcinitError> {
+	let mut sources = SourceStore::load()?;
+	if source != "local" && !sources.contains(source) {
+		return Err(SrcinitError::Source(String::from("Does not exist")));
+	} else if !confirm(cli, &format!("Remove source \"{}\" and its synced templates?", source))? {
+		return Err(SrcinitError::Aborted);
+	}
+
+	sources.remove(source).map_err(source_error)?;
+	sources.save()?;
+	status!("Removed source: \"{}\"", source);
+	return Ok(());
+}
+
+fn source_rename(source: &str, new_name: &str) -> Result<(), SrcinitError> {
+	let mut sources = SourceStore::load()?;
+	sources.rename(source, new_name).map_err(source_error)?;
+	sources.save()?;
+	status!("Renamed source: \"{}\" -> \"{}\"", source, new_name);
+	return Ok(());
+}
+
+// The mirror is only as fresh as the last sync, so the source is synced
+// first unless that's impossible
+fn source_mirror(cli: &Cli, source: &str, output: &Path) -> Result<(), SrcinitError> {
+	if output.read_dir().is_ok_and(|mut entries| entries.next().is_some()) {
+		return Err(SrcinitError::validation(format!("\"{}\" isn't empty", output.display())));
+	} else if output.exists() && !output.is_dir() {
+		return Err(SrcinitError::validation(format!("\"{}\" isn't a directory", output.display())));
+	}
+
+	let store = TemplateStore::open()?;
+	if !store.contains(source) {
+		return Err(SrcinitError::Source(String::from("Does not exist")));
+	}
+	if source != "local" && !cli.offline {
+		let failed = Mutex::new(None);
+		store.sync(Some(source), 1, |_, result| {
+			if let Err(error) = result {
+				*failed.lock().unwrap() = Some(error);
+			}
+		})?;
+		if let Some(error) = failed.into_inner().unwrap() {
+			return Err(error.into());
 		}
-		_ => {}
 	}
 
-	// TODO: Implement more stuff, if anyone can ;)
+	let templates = store.mirror(source, output)?;
+	if cli.json {
+		println!("{}", json!({ "source": source, "output": output, "templates": templates }));
+	} else {
+		status!("Mirrored: \"{}\" -> \"{}\" ({} templates)", source, output.display(), templates);
+	}
+	return Ok(());
+}
+
+fn source_toggle(source: &str, enable: bool) -> Result<(), SrcinitError> {
+	let mut sources = SourceStore::load()?;
+	sources.set_enabled(source, enable).map_err(source_error)?;
+	sources.save()?;
+
+	if enable {
+		status!("Enabled source: \"{}\"", source);
+	} else {
+		status!("Disabled source: \"{}\"", source);
+	}
+	return Ok(());
+}
+
+fn source_trust(source: &str, trust: bool) -> Result<(), SrcinitError> {
+	let mut sources = SourceStore::load()?;
+	sources.set_trusted(source, trust).map_err(source_error)?;
+	sources.save()?;
+
+	if trust {
+		status!("Trusted source: \"{}\"", source);
+	} else {
+		status!("Distrusted source: \"{}\"", source);
+	}
+	return Ok(());
+}
+
+fn source_login(source: &str) -> Result<(), SrcinitError> {
+	let sources = SourceStore::load()?;
+	if source == "local" || !sources.contains(source) {
+		return Err(SrcinitError::Source(String::from("Does not exist")));
+	}
+
+	// Without a terminal (e.g in CI), the token is piped in instead
+	let token = if io::stdin().is_terminal() {
+		Password::new()
+			.with_prompt(format!("Token for \"{}\"", source))
+			.interact()?
+	} else {
+		let mut token = String::new();
+		io::stdin().read_line(&mut token)?;
+		token.trim().to_string()
+	};
+	if token.is_empty() {
+		return Err(SrcinitError::validation("Token is empty"));
+	}
+	sources::auth::login(source, &token)?;
+	status!("Logged in: \"{}\"", source);
+	return Ok(());
+}
+
+fn source_logout(source: &str) -> Result<(), SrcinitError> {
+	sources::auth::logout(source)?;
+	status!("Logged out: \"{}\"", source);
+	return Ok(());
+}
+
+fn reset(cli: &Cli, force: bool) -> Result<(), SrcinitError> {
+	// If force isn't set or is set to false, we must confirm the user
+	// if they really want to wipe everything or not
+	if !force && !confirm(cli, "Perform a reset operation?")? {
+		// abort operation if user said no
+		return Err(SrcinitError::Aborted);
+	}
+
+	// Locate application data & cache directories
+	let dirs = vec![sources::dir_path(), sources::cache_path()];
+	let mut result = Ok(());
+	for directory in dirs {
+		if !directory.is_dir() {
+			notice!("Skipped: \"{}\" (already wiped)", directory.display());
+			continue;
+		}
+
+		if let Err(error) = fs::remove_dir_all(directory.clone()) {
+			failure!("Wipe failed: \"{}\" ({})", directory.display(), error);
+			result = Err(SrcinitError::Io(error));
+		} else {
+			status!("Wiped: \"{}\"", directory.display());
+		}
+	}
+	return result;
+}
+
+// The completion script only hooks the shell up to srcinit itself, which
+// then answers with candidates (see CompleteEnv in main)
+fn completions(shell: Shell) -> Result<(), SrcinitError> {
+	let shells = Shells::builtins();
+	let completer = shells.completer(&shell.to_string()).unwrap();
+	completer.write_registration("COMPLETE", "srcinit", "srcinit", "srcinit", &mut io::stdout())?;
+	return Ok(());
+}
+
+// Meant for packagers, who usually want every page written out at once
+fn man(output: &Option<PathBuf>) -> Result<(), SrcinitError> {
+	// Building propagates global flags (--offline, --json...) to every command
+	let mut command = Cli::command();
+	command.build();
+	let Some(output) = output else {
+		Man::new(command).render(&mut io::stdout())?;
+		return Ok(());
+	};
+
+	fs::create_dir_all(output)?;
+	for subcommand in command.get_subcommands().filter(|subcommand| !subcommand.is_hide_set() && subcommand.get_name() != "help") {
+		let name = format!("srcinit-{}", subcommand.get_name());
+		let mut page = Vec::new();
+		let subcommand = subcommand.clone()
+			.display_name(&name)
+			.version(env!("CARGO_PKG_VERSION"))
+			.bin_name(format!("srcinit {}", subcommand.get_name()));
+		Man::new(subcommand).render(&mut page)?;
+		fs::write(output.join(format!("{}.1", name)), page)?;
+	}
+
+	let mut page = Vec::new();
+	Man::new(command).render(&mut page)?;
+	fs::write(output.join("srcinit.1"), page)?;
+	status!("Wrote man pages: \"{}\"", output.display());
+	return Ok(());
+}
+
+// What a failed command reports it failed to do, and what it failed on
+fn describe(command: &Commands) -> (&'static str, Option<String>) {
+	return match command {
+		Commands::Generate { template, .. } => ("generate", template.clone()),
+		Commands::Sync { source, .. } => ("sync", source.clone()),
+		Commands::List { .. } => ("list templates", None),
+		Commands::Upgrade { path, .. } => ("upgrade", Some(path.display().to_string())),
+		Commands::Add { template, .. } => ("add", Some(template.clone())),
+		Commands::Render { file, .. } => ("render", file.as_ref().map(|file| file.display().to_string())),
+		Commands::Gitignore { output, .. } => ("compose gitignore", output.as_ref().map(|output| output.display().to_string())),
+		Commands::Regenerate { path, .. } => ("regenerate", Some(path.display().to_string())),
+		Commands::Info { template } => ("show template", Some(template.clone())),
+		Commands::Search { query } => ("search", Some(query.clone())),
+		Commands::SourceList {} => ("list sources", None),
+		Commands::Import { file } => ("import", Some(file.display().to_string())),
+		Commands::NewTemplate { name, .. } => ("create template", Some(name.clone())),
+		Commands::TemplateTest { template, .. } => ("test template", Some(template.clone())),
+		Commands::TemplateLint { template } => ("lint template", Some(template.clone())),
+		Commands::TemplateWatch { path, .. } => ("watch template", Some(path.display().to_string())),
+		Commands::Publish { template, .. } => ("publish", Some(template.clone())),
+		Commands::Serve { address } => ("serve", Some(address.clone())),
+		Commands::Export { template, .. } => ("export", Some(template.clone())),
+		Commands::Remove { template, .. } => ("remove template", Some(template.clone())),
+		Commands::SourceAdd { source, .. } => ("add new source", Some(source.clone())),
+		Commands::SourceEdit { source, .. } => ("edit existing source", Some(source.clone())),
+		Commands::SourceRemove { source } => ("remove source", Some(source.clone())),
+		Commands::SourceRename { source, .. } => ("rename source", Some(source.clone())),
+		Commands::SourceMirror { source, .. } => ("mirror source", Some(source.clone())),
+		Commands::SourceDisable { source } | Commands::SourceEnable { source } => ("change source", Some(source.clone())),
+		Commands::SourceTrust { source } | Commands::SourceDistrust { source } => ("change source", Some(source.clone())),
+		Commands::SourceLogin { source } => ("log in", Some(source.clone())),
+		Commands::SourceLogout { source } => ("log out", Some(source.clone())),
+		Commands::Reset { .. } => ("reset", None),
+		Commands::Completions { .. } => ("print completions", None),
+		Commands::Man { output } => ("write man pages", output.as_ref().map(|output| output.display().to_string())),
+	};
+}
+
+fn main() {
+	// Answers the shell when it's asking for completions, instead of running
+	CompleteEnv::with_factory(Cli::command).complete();
+
+	// Parse command-line arguments using the clap library. Usage errors
+	// exit with 1 like any other invalid input, instead of clap's own 2
+	let cli = Cli::try_parse().unwrap_or_else(|error| {
+		let _ = error.print();
+		process::exit(if error.use_stderr() { 1 } else { 0 });
+	});
+
+	// Only srcinit's own logs are raised by -v, since the HTTP & TLS crates
+	// underneath are very chatty. RUST_LOG still takes over when it's set
+	let level = match cli.verbose {
+		0 => LevelFilter::Warn,
+		1 => LevelFilter::Debug,
+		_ => LevelFilter::Trace,
+	};
+	if cli.quiet {
+		QUIET.store(true, Ordering::Relaxed);
+		srcinit::progress::hide();
+	}
+
+	// On auto, console already checks for a terminal & NO_COLOR by itself
+	let write_style = match cli.color {
+		ColorChoice::Auto => WriteStyle::Auto,
+		ColorChoice::Always => WriteStyle::Always,
+		ColorChoice::Never => WriteStyle::Never,
+	};
+	if !matches!(cli.color, ColorChoice::Auto) {
+		let enabled = matches!(cli.color, ColorChoice::Always);
+		console::set_colors_enabled(enabled);
+		console::set_colors_enabled_stderr(enabled);
+	}
+
+	env_logger::Builder::new()
+		.filter_level(LevelFilter::Warn)
+		.filter_module("srcinit", level)
+		.parse_env("RUST_LOG")
+		.format_timestamp(None)
+		.write_style(write_style)
+		.init();
+	let Some(command) = &cli.command else {
+		return;
+	};
+
+	// Once parsed, we can use match statements to call different functions
+	// (e.g if "generate" is the subcommand, then we go to the generate handler)
+	let result = match command {
+		Commands::Generate { template, addons, output, archive, defines, values, license, on_conflict, dry_run, no_remember, follow_symlinks, filter, hooks, git } => {
+			generate(&cli, template, addons, output, archive, defines, values, license, *on_conflict, *dry_run, !*no_remember, *follow_symlinks, filter, hooks, git)
+		}
+		Commands::Sync { source, jobs, retries, check } => sync(&cli, source, *jobs, *retries, *check),
+		Commands::List { local } => list(&cli, *local),
+		Commands::Upgrade { path, defines } => upgrade(&cli, path, defines),
+		Commands::Add { template, path, defines, values, force, hooks } => add(&cli, template, path, defines, values, *force, hooks),
+		Commands::Render { file, stdin, defines, values } => render(file, *stdin, defines, values),
+		Commands::Gitignore { names, output, list } => gitignore(&cli, names, output, *list),
+		Commands::Regenerate { path, defines, on_conflict } => regenerate(&cli, path, defines, *on_conflict),
+		Commands::Info { template } => info(&cli, template),
+		Commands::Search { query } => search(&cli, query),
+		Commands::SourceList {} => source_list(&cli),
+		Commands::Import { file } => import(file),
+		Commands::NewTemplate { name, output } => new_template(name, output),
+		Commands::TemplateTest { template, update } => template_test(&cli, template, *update),
+		Commands::TemplateLint { template } => template_lint(&cli, template),
+		Commands::TemplateWatch { path, output, defines, values } => template_watch(path, output, defines, values),
+		Commands::Publish { template, source, bump } => publish(&cli, template, source, *bump),
+		Commands::Serve { address } => serve(address),
+		Commands::Export { template, output } => export(template, output),
+		Commands::Remove { template, force } => remove(&cli, template, *force),
+		Commands::SourceAdd { source, url, public_key, token_env, mirrors, proxy, ca_cert, keep_versions, upload_url } => {
+			source_add(source, url, public_key, token_env, mirrors, proxy, ca_cert, *keep_versions, upload_url)
+		}
+		Commands::SourceEdit { source, new_url } => source_edit(source, new_url),
+		Commands::SourceRemove { source } => source_remove(&cli, source),
+		Commands::SourceRename { source, new_name } => source_rename(source, new_name),
+		Commands::SourceMirror { source, output } => source_mirror(&cli, source, output),
+		Commands::SourceDisable { source } => source_toggle(source, false),
+		Commands::SourceEnable { source } => source_toggle(source, true),
+		Commands::SourceTrust { source } => source_trust(source, true),
+		Commands::SourceDistrust { source } => source_trust(source, false),
+		Commands::SourceLogin { source } => source_login(source),
+		Commands::SourceLogout { source } => source_logout(source),
+		Commands::Reset { force } => reset(&cli, *force),
+		Commands::Completions { shell } => completions(*shell),
+		Commands::Man { output } => man(output),
+	};
+
+	if let Err(error) = result {
+		match describe(command) {
+			(action, subject) if cli.json => println!("{}", json!({
+				"error": { "action": action, "subject": subject, "message": error.to_string(), "code": error.exit_code() },
+			})),
+			(action, Some(subject)) => failure!("Failed to {}: \"{}\" ({})", action, subject, error),
+			(action, None) => failure!("Failed to {} ({})", action, error),
+		}
+		process::exit(error.exit_code());
+	}
 }
@@ -0,0 +1,19 @@
+use indicatif::ProgressDrawTarget;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Progress bars are drawn on stderr (when it's a terminal), unless they were
+// turned off, e.g. by --quiet
+static HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// Stops drawing progress bars for the rest of the process.
+pub fn hide() {
+    HIDDEN.store(true, Ordering::Relaxed);
+}
+
+pub fn target() -> ProgressDrawTarget {
+    if HIDDEN.load(Ordering::Relaxed) {
+        return ProgressDrawTarget::hidden();
+    }
+
+    return ProgressDrawTarget::stderr();
+}
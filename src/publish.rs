@@ -0,0 +1,179 @@
+use crate::{archive, sources::{self, auth, remote, Kind, Source}, template::{self, Ignore, Manifest, MANIFEST}};
+use indicatif::ProgressBar;
+use semver::Version;
+use std::{collections::HashMap, fs, io::{Error as IoError, ErrorKind, Result as IoResult}, path::Path};
+
+/// Which part of a template's version [`bump`] increments.
+#[derive(Clone, Copy)]
+pub enum Level {
+    Major,
+    Minor,
+    Patch,
+}
+
+/// Increments the version in the manifest of the template in `root`, which
+/// starts from 0.0.0 when it has none yet. Only that one line of the
+/// manifest is rewritten, so its comments and layout stay as they were.
+pub fn bump(root: &Path, level: Level) -> IoResult<Version> {
+    let current = match Manifest::read(root)?.version {
+        Some(version) => template::parse_version(&version)
+            .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, format!("version \"{}\" isn't a semantic version", version)))?,
+        None => Version::new(0, 0, 0),
+    };
+    let version = match level {
+        Level::Major => Version::new(current.major + 1, 0, 0),
+        Level::Minor => Version::new(current.major, current.minor + 1, 0),
+        Level::Patch => Version::new(current.major, current.minor, current.patch + 1),
+    };
+
+    let path = root.join(MANIFEST);
+    let content = if path.is_file() { fs::read_to_string(&path)? } else { String::new() };
+    fs::write(path, set_version(&content, &version))?;
+    return Ok(version);
+}
+
+// Replaces the top-level version of a manifest (the one before any table),
+// or adds one right after the name
+fn set_version(content: &str, version: &Version) -> String {
+    let is_key = |line: &str, name: &str| line.split_once('=').is_some_and(|(key, _)| key.trim() == name);
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let top = lines.iter().position(|line| line.trim_start().starts_with('[')).unwrap_or(lines.len());
+    let line = format!("version = \"{}\"", version);
+    match lines[..top].iter().position(|line| is_key(line, "version")) {
+        Some(index) => lines[index] = line,
+        None => {
+            let after = lines[..top].iter().position(|line| is_key(line, "name")).map_or(0, |index| index + 1);
+            lines.insert(after, line);
+        }
+    }
+
+    return lines.join("\n") + "\n";
+}
+
+/// Publishes the template in `root` (its own directory, not merged with the
+/// templates it extends) to a source, returning the version published.
+///
+/// The template has to have a version newer than the one the source
+/// currently provides. Git sources get it committed & pushed as their
+/// `<name>/` directory, directory sources get it copied there, and HTTP
+/// sources get it uploaded to their `upload_url` as
+/// `<name>/<version>.tar.gz`, with the source's credentials.
+pub fn publish(root: &Path, source: &Source) -> IoResult<Version> {
+    if source.is_local() {
+        return Err(IoError::new(ErrorKind::InvalidInput, "the local source can't be published to, import the template instead"));
+    }
+
+    let manifest = Manifest::read(root)?;
+    let name = manifest.name.unwrap_or_else(|| root.file_name().unwrap_or_default().to_string_lossy().to_string());
+    if !template::is_valid_name(&name) {
+        return Err(IoError::new(ErrorKind::InvalidInput, format!("invalid template name \"{}\"", name)));
+    }
+    let version = manifest.version.as_deref().and_then(template::parse_version)
+        .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, "templates need a semantic version to be published"))?;
+    let ignore = Ignore::read(root)?;
+
+    if let Some(dir) = sources::directory(&source.url) {
+        publish_dir(root, &dir.join(&name), &version, &ignore)?;
+        return Ok(version);
+    }
+
+    let staging = sources::cache_path().join(".publish").join(format!("{:08x}", fastrand::u32(..)));
+    let result = match source.kind {
+        Kind::Git => publish_git(root, &name, &version, &ignore, source, &staging),
+        _ => publish_http(root, &name, &version, &ignore, source, &staging),
+    };
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+
+    return result.map(|_| version);
+}
+
+fn is_newer(version: &Version, published: Option<&str>) -> IoResult<()> {
+    return match published.and_then(template::parse_version) {
+        Some(published) if &published >= version => Err(IoError::new(
+            ErrorKind::InvalidInput,
+            format!("version {} isn't newer than the published {}", version, published),
+        )),
+        _ => Ok(()),
+    };
+}
+
+// Copied next to the published template first, so it's only ever replaced
+// as a whole
+fn publish_dir(root: &Path, target: &Path, version: &Version, ignore: &Ignore) -> IoResult<()> {
+    is_newer(version, Manifest::read(target)?.version.as_deref())?;
+
+    let partial = target.with_file_name(format!(".{}.publish", target.file_name().unwrap().to_string_lossy()));
+    if partial.exists() {
+        fs::remove_dir_all(&partial)?;
+    }
+    template::copy_dir(root, &partial, Some(ignore))?;
+    if target.exists() {
+        fs::remove_dir_all(target)?;
+    }
+    return fs::rename(partial, target);
+}
+
+// Git sources authenticate the same way they're synced, through git's own
+// credentials (SSH keys, credential helpers...)
+fn publish_git(root: &Path, name: &str, version: &Version, ignore: &Ignore, source: &Source, staging: &Path) -> IoResult<()> {
+    let mut clone = sources::git(source)?;
+    clone.args(["clone", "--depth", "1", "--quiet", source.url.strip_prefix("git+").unwrap()]).arg(staging);
+    sources::run_git(clone)?;
+
+    let target = staging.join(name);
+    is_newer(version, Manifest::read(&target)?.version.as_deref())?;
+    if target.exists() {
+        fs::remove_dir_all(&target)?;
+    }
+    template::copy_dir(root, &target, Some(ignore))?;
+
+    let message = format!("Publish {} {}", name, version);
+    for args in [vec!["add", "--all", "--", name], vec!["commit", "--quiet", "--message", &message], vec!["push", "--quiet"]] {
+        let mut git = sources::git(source)?;
+        git.arg("-C").arg(staging).args(args);
+        sources::run_git(git)?;
+    }
+
+    return Ok(());
+}
+
+// The index is read from the source URL itself rather than the cache, which
+// may well be out of date. Adding the upload to the index is up to the server
+fn publish_http(root: &Path, name: &str, version: &Version, ignore: &Ignore, source: &Source, staging: &Path) -> IoResult<()> {
+    let Some(upload_url) = &source.upload_url else {
+        return Err(IoError::new(ErrorKind::Unsupported, "the source has no upload URL (see source-add --upload-url)"));
+    };
+
+    let auth = auth::resolve(source)?;
+    if let Some((_, index, _)) = remote::fetch_index(&source.url, source, auth.as_ref(), None, &ProgressBar::hidden())? {
+        is_newer(version, index.templates.iter().find(|entry| entry.name == name).and_then(|entry| entry.version.as_deref()))?;
+    }
+
+    // Synced templates are unpacked straight into their directory, so the
+    // tarball has no top-level directory of its own
+    fs::create_dir_all(staging)?;
+    let file = staging.join(format!("{}.tar.gz", name));
+    archive::pack(root, ".", &file, &[], &HashMap::new(), |relative, is_dir| ignore.matches(relative, is_dir))?;
+
+    let target = remote::resolve(upload_url, &format!("{}/{}.tar.gz", name, version));
+    return remote::upload(&target, source, auth.as_ref(), &fs::read(&file)?);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sets_the_top_level_version() {
+        let version = Version::new(1, 2, 0);
+        assert_eq!(set_version("name = \"t\"\nversion = \"1.1.0\"\n", &version), "name = \"t\"\nversion = \"1.2.0\"\n");
+        assert_eq!(set_version("description = \"d\"\nname = \"t\"\n", &version), "description = \"d\"\nname = \"t\"\nversion = \"1.2.0\"\n");
+        assert_eq!(set_version("", &version), "version = \"1.2.0\"\n");
+
+        // Keys of tables (like a computed value named version) are left alone
+        let content = "name = \"t\"\n\n[[variables]]\nname = \"major\"\ndefault = \"0\"\n\n[computed]\nversion = \"{{ major }}.1.0\"\n";
+        assert_eq!(set_version(content, &version), "name = \"t\"\nversion = \"1.2.0\"\n\n[[variables]]\nname = \"major\"\ndefault = \"0\"\n\n[computed]\nversion = \"{{ major }}.1.0\"\n");
+    }
+}
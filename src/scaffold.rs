@@ -0,0 +1,72 @@
+use crate::template::{self, IGNORE_FILE, MANIFEST};
+use std::{fs, io::{Error as IoError, ErrorKind, Result as IoResult}, path::{Path, PathBuf}};
+
+// The manifest of a new template, showing off what templates can do with
+// every setting either used or commented out
+const SKELETON_MANIFEST: &str = r#"name = "{name}"
+description = "What projects generated from this template are"
+version = "0.1.0"
+# author = "Your Name <you@example.com>"
+tags = []
+
+# Run in the template directory before anything is written, with the
+# answers as JSON on stdin; failing rejects them
+pre_gen = ["sh hooks/pre_gen.sh"]
+# Run in the generated project once it's written
+# post_gen = ["git init --quiet"]
+
+post_message = "Next steps: cd {{ project_name }}"
+# Written as LICENSE, filled in with the year & author ("none" writes none)
+license = "{{ license }}"
+
+# Asked for when generating, and available in every file & file name as
+# {{ name }}. `project_name` is always there too, named after the output
+# directory (see "Variables" in srcinit's README for every type)
+[[variables]]
+name = "description"
+help = "What the project is about"
+default = "A new project"
+
+[[variables]]
+name = "license"
+type = "choice"
+choices = ["MIT", "Apache-2.0", "none"]
+default = "MIT"
+
+# Values derived from the answers, rendered in order
+[computed]
+title = "{{ project_name | pascal_case }}"
+
+# Files only generated when their condition holds
+# [[files]]
+# path = "docs"
+# when = "description != ''"
+"#;
+
+const SKELETON_FILES: &[(&str, &str)] = &[
+    ("README.md", "# {{ title }}\n\n{{ description }}\n{% if license != \"none\" %}\n## License\n\n{{ license }}, see [LICENSE](LICENSE).\n{% endif %}"),
+    ("hooks/pre_gen.sh", "#!/bin/sh\n# Answers arrive as JSON on stdin, and as SRCINIT_VAR_<NAME> variables.\n# Exit with an error (saying why on stderr) to reject them\nexit 0\n"),
+    (IGNORE_FILE, "# Files of the template that are never generated\nhooks/\n"),
+];
+
+/// Creates a starter template named `name` in `dir`, with a manifest
+/// declaring a few example variables, files using them and a hook stub.
+/// Returns where it was created.
+pub fn scaffold(name: &str, dir: &Path) -> IoResult<PathBuf> {
+    if !template::is_valid_name(name) {
+        return Err(IoError::new(ErrorKind::InvalidInput, "template names can't be empty, contain slashes or start with a dot"));
+    }
+
+    let path = dir.join(name);
+    if path.exists() {
+        return Err(IoError::new(ErrorKind::InvalidInput, format!("\"{}\" already exists", path.display())));
+    }
+
+    fs::create_dir_all(path.join("hooks"))?;
+    fs::write(path.join(MANIFEST), SKELETON_MANIFEST.replace("{name}", name))?;
+    for (file, content) in SKELETON_FILES {
+        fs::write(path.join(file), content)?;
+    }
+
+    return Ok(path);
+}
@@ -0,0 +1,147 @@
+use crate::{archive, sources::{self, remote::{self, Entry, Index, FORMAT_VERSION, INDEX}}, template::{self, Ignore}};
+use log::{debug, trace};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Result as IoResult, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    thread,
+};
+
+// Where the packed templates are kept while serving, named after their
+// checksum so a download never gets another template than the index said
+const ARCHIVES: &str = "archives";
+
+/// Serves the templates of a source in the format of an HTTP source (see
+/// "Remote sources" in README.md), answering each connection on a thread of
+/// its own. Only returns when the listener fails.
+///
+/// Templates are packed again whenever the index is asked for, so changes
+/// made to them while serving are picked up by the next sync.
+pub fn serve(listener: TcpListener, source: &str) -> IoResult<()> {
+    let dir = sources::cache_path().join(".serve");
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(dir.join(ARCHIVES))?;
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let (source, dir) = (source.to_string(), dir.clone());
+        thread::spawn(move || {
+            if let Err(error) = answer(stream, &source, &dir) {
+                debug!("Failed to answer a request ({})", error);
+            }
+        });
+    }
+
+    return Ok(());
+}
+
+fn answer(mut stream: TcpStream, source: &str, dir: &Path) -> IoResult<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or_default().to_string(), parts.next().unwrap_or_default().to_string());
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    trace!("{} {} with {:?}", method, target, headers);
+
+    let path = target.split('?').next().unwrap_or_default().trim_start_matches('/');
+    let mut etag = None;
+    let (status, content_type, body) = match (method.as_str(), path) {
+        ("GET" | "HEAD", INDEX | "") => match index(source, dir) {
+            Ok(index) => {
+                // Quotes are left out of ETags by some clients, so they're optional
+                let checksum = remote::sha256(&index);
+                let is_cached = headers.get("if-none-match").is_some_and(|tag| tag.trim_start_matches("W/").trim_matches('"') == checksum);
+                let status = if is_cached { "304 Not Modified" } else { "200 OK" };
+                etag = Some(format!("\"{}\"", checksum));
+                (status, "application/json", index)
+            }
+            Err(error) => ("500 Internal Server Error", "text/plain", format!("{}\n", error).into_bytes()),
+        },
+        ("GET" | "HEAD", path) => match archive_path(dir, path).filter(|path| path.is_file()) {
+            Some(path) => ("200 OK", "application/gzip", fs::read(path)?),
+            None => ("404 Not Found", "text/plain", b"Not found\n".to_vec()),
+        },
+        _ => ("405 Method Not Allowed", "text/plain", b"Method not allowed\n".to_vec()),
+    };
+
+    debug!("{} {} -> {}", method, target, status);
+    let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\nContent-Type: {}\r\n", status, content_type);
+    if let Some(etag) = etag {
+        response.push_str(&format!("ETag: {}\r\n", etag));
+    }
+    // Nothing follows the headers of a 304, and HEAD only gets the length
+    let body = if status.starts_with("304") { &[][..] } else { &body[..] };
+    response.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+    stream.write_all(response.as_bytes())?;
+    if method != "HEAD" {
+        stream.write_all(body)?;
+    }
+    return stream.flush();
+}
+
+// Only ever the checksum of an archive we packed, so no path outside of
+// the archives directory can be asked for
+fn archive_path(dir: &Path, path: &str) -> Option<PathBuf> {
+    let checksum = path.strip_prefix(&format!("{}/", ARCHIVES))?.strip_suffix(".tar.gz")?;
+    if checksum.len() != 64 || !checksum.chars().all(|char| char.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    return Some(dir.join(ARCHIVES).join(format!("{}.tar.gz", checksum)));
+}
+
+// Packs every template of the source the way synced ones are unpacked, with
+// no top-level directory, leaving out what they ignore
+fn index(source: &str, dir: &Path) -> IoResult<Vec<u8>> {
+    let mut templates = Vec::new();
+    for template in template::list(source) {
+        let ignore = Ignore::read(&template.path)?;
+        let partial = dir.join(format!(".{:08x}.partial", fastrand::u32(..)));
+        archive::pack(&template.path, ".", &partial, &[], &HashMap::new(), |relative, is_dir| ignore.matches(relative, is_dir))?;
+        let checksum = remote::sha256(&fs::read(&partial)?);
+        fs::rename(&partial, dir.join(ARCHIVES).join(format!("{}.tar.gz", checksum)))?;
+
+        templates.push(Entry {
+            name: template.name,
+            version: template.manifest.version,
+            description: template.manifest.description,
+            url: format!("{}/{}.tar.gz", ARCHIVES, checksum),
+            sha256: checksum,
+        });
+    }
+
+    return serde_json::to_vec_pretty(&Index { version: FORMAT_VERSION, templates }).map_err(Into::into);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_serves_packed_archives() {
+        let dir = Path::new("/srv");
+        let checksum = "0123456789abcdef".repeat(4);
+        let path = format!("{}/{}.tar.gz", ARCHIVES, checksum);
+        assert_eq!(archive_path(dir, &path), Some(dir.join(ARCHIVES).join(format!("{}.tar.gz", checksum))));
+
+        assert_eq!(archive_path(dir, &format!("{}/../index.tar.gz", ARCHIVES)), None);
+        assert_eq!(archive_path(dir, &format!("{}/{}.tar.gz", ARCHIVES, &checksum[1..])), None);
+        assert_eq!(archive_path(dir, &format!("{}/{}.tar.gz", ARCHIVES, checksum.replace('0', "g"))), None);
+        assert_eq!(archive_path(dir, &format!("{}.tar.gz", checksum)), None);
+    }
+}
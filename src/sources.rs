@@ -1,29 +1,769 @@
-use crate::built_info;
+pub mod auth;
+pub mod remote;
+
+use crate::{built_info, progress, sources::remote::Change, template};
+use chrono::{DateTime, Utc};
 use directories::ProjectDirs;
-use ini::{Error, Ini};
-use std::{fs , io::Result as IoResult, path::PathBuf};
+use ini::Ini;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use is_url::is_url;
+use log::{debug, trace};
+use minisign_verify::PublicKey;
+use serde::{Deserialize, Serialize};
+use std::{fs , io::{Error as IoError, ErrorKind, Result as IoResult}, path::{self, Path, PathBuf}, process::Command, sync::Mutex, thread, time::Duration};
+use ureq::{http::Uri, Proxy};
+
+// Where sources were kept before sources.toml, which is migrated on first
+// use and then kept around as sources.ini.bak
+const LEGACY_FILE: &str = "sources.ini";
+// Written into the cache of a source whenever it was synced, before the
+// time was recorded in sources.toml instead
+const LEGACY_LAST_SYNCED: &str = ".last_synced";
+
+/// What a source is, as told by its URL.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Git,
+    #[default]
+    Http,
+    Directory,
+}
+
+impl Kind {
+    pub fn of(url: &str) -> Kind {
+        if directory(url).is_some() {
+            return Kind::Directory;
+        } else if is_git(url) {
+            return Kind::Git;
+        }
+        return Kind::Http;
+    }
+}
+
+/// A configured source of templates, as stored in `sources.toml`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Source {
+    pub name: String,
+    pub url: String,
+    /// Derived from the URL, only written down for whoever reads the file.
+    #[serde(skip_deserializing)]
+    pub kind: Kind,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    /// Whether hooks of the source's templates run without asking first.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub trusted: bool,
+    /// Where the credentials are, never the credentials themselves: only
+    /// `env:<VARIABLE>` for now, the keyring and `credentials.ini` being
+    /// looked up by the name of the source anyway.
+    pub auth: Option<String>,
+    /// URLs tried in order when an HTTP source is unavailable.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
+    /// Minisign public key the index of an HTTP source is verified with.
+    pub public_key: Option<String>,
+    /// Used instead of the environment's, "none" to connect directly.
+    pub proxy: Option<String>,
+    pub ca_cert: Option<String>,
+    pub keep_versions: Option<usize>,
+    /// Where templates are published to, for HTTP sources.
+    pub upload_url: Option<String>,
+    last_synced: Option<String>,
+}
+
+fn enabled() -> bool {
+    return true;
+}
+
+fn is_false(value: &bool) -> bool {
+    return !value;
+}
+
+impl Source {
+    /// A source with the default settings. URLs are taken as they are, see
+    /// [`SourceStore::add`] for the checks they go through.
+    pub fn new(name: impl Into<String>, url: impl Into<String>) -> Source {
+        let url = url.into();
+        return Source {
+            name: name.into(),
+            kind: Kind::of(&url),
+            url,
+            enabled: true,
+            trusted: false,
+            auth: None,
+            mirrors: Vec::new(),
+            public_key: None,
+            proxy: None,
+            ca_cert: None,
+            keep_versions: None,
+            upload_url: None,
+            last_synced: None,
+        };
+    }
+
+    // The user's own templates, in srcinit's config directory
+    fn local() -> Source {
+        return Source { kind: Kind::Directory, ..Source::new("local", "LOCAL") };
+    }
+
+    pub fn is_local(&self) -> bool {
+        return self.name == "local";
+    }
+
+    // Templates of the local source are the user's own, so it's always trusted
+    pub fn is_trusted(&self) -> bool {
+        return self.is_local() || self.trusted;
+    }
+
+    /// The environment variable holding a bearer token for the source.
+    pub fn token_env(&self) -> Option<&str> {
+        return self.auth.as_deref().and_then(|auth| auth.strip_prefix("env:"));
+    }
+
+    /// The URL of the source followed by its mirrors, in the order they're
+    /// tried.
+    pub fn urls(&self) -> Vec<String> {
+        return [self.url.clone()].into_iter().chain(self.mirrors.iter().cloned()).collect();
+    }
+
+    pub fn templates_path(&self) -> PathBuf {
+        if self.is_local() {
+            return dir_path().join("templates");
+        }
+
+        // Directory sources are read in place rather than copied into the
+        // cache, so template authors always get their latest changes
+        return directory(&self.url).unwrap_or_else(|| cache_path().join(&self.name));
+    }
+
+    /// When the source was last synced, as long as the templates synced then
+    /// are still in the cache.
+    pub fn last_synced(&self) -> Option<DateTime<Utc>> {
+        let time = self.last_synced.as_deref()?;
+        if !self.templates_path().is_dir() {
+            return None;
+        }
+        return DateTime::parse_from_rfc3339(time).ok().map(|time| time.with_timezone(&Utc));
+    }
+
+    // Settings only make sense for some kinds of sources, and are checked
+    // before the source is stored rather than when it's first synced
+    fn validate(&self) -> IoResult<()> {
+        let invalid = |message: String| Err(IoError::new(ErrorKind::InvalidInput, message));
+        validate_name(&self.name)?;
+        if !is_valid_url(&self.url) {
+            return invalid(String::from("URL malformed or invalid"));
+        }
+
+        let is_http = self.kind == Kind::Http;
+        if !self.mirrors.is_empty() && !is_http {
+            return invalid(String::from("Mirrors are only supported for HTTP sources"));
+        } else if let Some(mirror) = self.mirrors.iter().find(|mirror| !is_valid_url(mirror) || directory(mirror).is_some()) {
+            return invalid(format!("Mirror \"{}\" malformed or invalid", mirror));
+        }
+
+        if let Some(proxy) = &self.proxy {
+            if self.kind == Kind::Directory {
+                return invalid(String::from("Proxies are only supported for remote sources"));
+            } else if proxy != "none" && Proxy::new(proxy).is_err() {
+                return invalid(format!("Proxy \"{}\" malformed or invalid", proxy));
+            }
+        }
+
+        if let Some(ca_cert) = &self.ca_cert {
+            if self.kind == Kind::Directory {
+                return invalid(String::from("CA certificates are only supported for remote sources"));
+            }
+            remote::certificates(Path::new(ca_cert))?;
+        }
+
+        if let Some(public_key) = &self.public_key {
+            if !is_http {
+                return invalid(String::from("Public keys are only supported for HTTP sources"));
+            } else if PublicKey::from_base64(public_key).is_err() {
+                return invalid(String::from("Public key malformed or invalid"));
+            }
+        }
+
+        if let Some(upload_url) = &self.upload_url {
+            if !is_http {
+                return invalid(String::from("Upload URLs are only supported for HTTP sources, the others are published to directly"));
+            } else if !is_valid_url(upload_url) || directory(upload_url).is_some() {
+                return invalid(format!("Upload URL \"{}\" malformed or invalid", upload_url));
+            }
+        }
+
+        if let Some(auth) = &self.auth {
+            if self.token_env().is_none_or(str::is_empty) {
+                return invalid(format!("Auth \"{}\" malformed or invalid, expected env:<VARIABLE>", auth));
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+// Names are used in "source/template" names and as directory names in the
+// cache, so they're kept to something that works as both
+fn validate_name(name: &str) -> IoResult<()> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\', '@']) || name.chars().any(char::is_whitespace) {
+        return Err(IoError::new(ErrorKind::InvalidInput, format!("Name \"{}\" can't be empty, start with \".\" or contain \"/\", \"@\" or spaces", name)));
+    }
+    return Ok(());
+}
 
-pub fn init() -> Result<Ini, Error> {
-    return Ini::load_from_file(path());
+// The layout of sources.toml, one [[sources]] table per source in the order
+// they were added. The local source is always there and so isn't stored
+#[derive(Deserialize, Serialize)]
+struct SourcesFile {
+    #[serde(default)]
+    sources: Vec<Source>,
 }
 
-pub fn new() -> Ini {
-    let mut ini = Ini::new();
-    ini.with_section(None::<String>).add("local", "LOCAL");
-    return ini;
+/// Every configured source, "local" first and then the others in the order
+/// they were added.
+///
+/// Changes are checked as they're made, but only written to `sources.toml`
+/// once [`SourceStore::save`] is called.
+#[derive(Clone, Debug)]
+pub struct SourceStore {
+    sources: Vec<Source>,
 }
 
-pub fn write(ini: Ini) -> IoResult<()> {
-    let path = path();
-    fs::create_dir_all(path.parent().unwrap())?;
-    return ini.write_to_file(path);
+impl Default for SourceStore {
+    fn default() -> SourceStore {
+        return SourceStore { sources: vec![Source::local()] };
+    }
+}
+
+impl SourceStore {
+    /// Loads the configured sources, starting from just "local" when none
+    /// were configured yet. A `sources.ini` left from older versions is
+    /// migrated to `sources.toml` first.
+    pub fn load() -> IoResult<SourceStore> {
+        let path = path();
+        if !path.exists() && dir_path().join(LEGACY_FILE).is_file() {
+            migrate()?;
+        }
+
+        trace!("Loading sources from \"{}\"", path.display());
+        let content = match fs::read_to_string(&path) {
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(SourceStore::default()),
+            result => result?,
+        };
+        let invalid = |message: String| IoError::new(ErrorKind::InvalidData, format!("invalid sources.toml: {}", message));
+        let file: SourcesFile = toml::from_str(&content).map_err(|error| invalid(error.message().to_string()))?;
+
+        let mut store = SourceStore::default();
+        for mut source in file.sources {
+            source.kind = Kind::of(&source.url);
+            if store.contains(&source.name) {
+                return Err(invalid(format!("source \"{}\" is declared more than once", source.name)));
+            } else if source.auth.is_some() && source.token_env().is_none() {
+                return Err(invalid(format!("unknown auth of \"{}\", expected env:<VARIABLE>", source.name)));
+            }
+            store.sources.push(source);
+        }
+        return Ok(store);
+    }
+
+    /// Writes the sources back to `sources.toml`.
+    pub fn save(&self) -> IoResult<()> {
+        let path = path();
+        fs::create_dir_all(path.parent().unwrap())?;
+        let file = SourcesFile { sources: self.sources.iter().filter(|source| !source.is_local()).cloned().collect() };
+        let content = toml::to_string(&file).map_err(IoError::other)?;
+
+        // Written next to the file first, so it's never left half-written
+        let partial = path.with_extension("toml.partial");
+        fs::write(&partial, content)?;
+        return fs::rename(partial, path);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Source> {
+        return self.sources.iter();
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Source> {
+        return self.sources.iter().find(|source| source.name == name);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        return self.get(name).is_some();
+    }
+
+    /// Adds a source after checking its URL & settings. Directory sources
+    /// are stored as absolute `file://` URLs and CA certificates as absolute
+    /// paths, so they keep working no matter where srcinit is run from.
+    pub fn add(&mut self, mut source: Source) -> IoResult<&Source> {
+        if self.contains(&source.name) {
+            return Err(IoError::new(ErrorKind::InvalidInput, "Already exists"));
+        }
+        source.kind = Kind::of(&source.url);
+        source.validate()?;
+
+        source.url = normalize_url(&source.url)?;
+        if let Some(ca_cert) = &source.ca_cert {
+            source.ca_cert = Some(fs::canonicalize(ca_cert)?.to_string_lossy().to_string());
+        }
+        self.sources.push(source);
+        return Ok(self.sources.last().unwrap());
+    }
+
+    /// Points a source at another URL, keeping its settings (which have to
+    /// make sense for the new URL too).
+    pub fn edit(&mut self, name: &str, url: &str) -> IoResult<&Source> {
+        if name == "local" {
+            return Err(IoError::new(ErrorKind::InvalidInput, "The local source can't be edited"));
+        }
+        let source = self.get_mut(name)?;
+        if !is_valid_url(url) {
+            return Err(IoError::new(ErrorKind::InvalidInput, "New URL malformed or invalid"));
+        }
+        let mut edited = Source { url: url.to_string(), kind: Kind::of(url), ..source.clone() };
+        edited.validate()?;
+
+        edited.url = normalize_url(url)?;
+        *source = edited;
+        return Ok(source);
+    }
+
+    /// Renames every trace of a source: its entry, its credentials and its
+    /// synced templates, so nothing has to be synced again afterwards.
+    pub fn rename(&mut self, old: &str, new: &str) -> IoResult<()> {
+        if old == "local" || new == "local" {
+            return Err(IoError::new(ErrorKind::InvalidInput, "The local source can't be renamed"));
+        } else if self.contains(new) {
+            return Err(IoError::new(ErrorKind::InvalidInput, format!("\"{}\" already exists", new)));
+        }
+        validate_name(new)?;
+        self.get_mut(old)?.name = new.to_string();
+
+        let credentials_path = auth::path();
+        if credentials_path.is_file() {
+            let mut credentials = Ini::load_from_file(&credentials_path).map_err(IoError::other)?;
+            if credentials.section(Some(old)).is_some() {
+                rename_section(&mut credentials, old, new);
+                credentials.write_to_file(&credentials_path)?;
+            }
+        }
+
+        if let Some(token) = auth::keyring_entry(old).ok().and_then(|entry| entry.get_password().ok()) {
+            auth::login(new, &token)?;
+            auth::logout(old)?;
+        }
+
+        for (old, new) in [(cache_path().join(old), cache_path().join(new)), (versions_path(old), versions_path(new))] {
+            if old.exists() {
+                fs::rename(old, new)?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Removes a source along with every trace of it: its synced templates,
+    /// the versions kept of them and its credentials. Directory sources are
+    /// left on disk, as they were never copied in the first place.
+    pub fn remove(&mut self, name: &str) -> IoResult<Source> {
+        if name == "local" {
+            return Err(IoError::new(ErrorKind::InvalidInput, "The local source can't be removed"));
+        }
+        let index = self.sources.iter().position(|source| source.name == name)
+            .ok_or_else(|| IoError::new(ErrorKind::NotFound, "Does not exist"))?;
+        let source = self.sources.remove(index);
+
+        let credentials_path = auth::path();
+        if credentials_path.is_file() {
+            let mut credentials = Ini::load_from_file(&credentials_path).map_err(IoError::other)?;
+            if credentials.delete(Some(name)).is_some() {
+                credentials.write_to_file(&credentials_path)?;
+            }
+        }
+        if auth::keyring_entry(name).ok().and_then(|entry| entry.get_password().ok()).is_some() {
+            auth::logout(name)?;
+        }
+
+        for path in [cache_path().join(name), versions_path(name)] {
+            if path.exists() {
+                fs::remove_dir_all(path)?;
+            }
+        }
+
+        return Ok(source);
+    }
+
+    /// Disabled sources are left out of syncing, listing & generation.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> IoResult<()> {
+        if name == "local" {
+            return Err(IoError::new(ErrorKind::InvalidInput, "The local source is always enabled"));
+        }
+        self.get_mut(name)?.enabled = enabled;
+        return Ok(());
+    }
+
+    pub fn set_trusted(&mut self, name: &str, trusted: bool) -> IoResult<()> {
+        if name == "local" {
+            return Err(IoError::new(ErrorKind::InvalidInput, "The local source is always trusted"));
+        }
+        self.get_mut(name)?.trusted = trusted;
+        return Ok(());
+    }
+
+    fn get_mut(&mut self, name: &str) -> IoResult<&mut Source> {
+        return self.sources.iter_mut()
+            .find(|source| source.name == name)
+            .ok_or_else(|| IoError::new(ErrorKind::NotFound, "Does not exist"));
+    }
 }
 
 pub fn path() -> PathBuf {
-    return dir_path().join("sources.ini");
+    return dir_path().join("sources.toml");
+}
+
+// Moves sources.ini over to sources.toml, along with the time every source
+// was last synced (which used to be kept with its templates). Every source
+// used to be in the general section, with its settings in a section of its
+// own
+fn migrate() -> IoResult<()> {
+    let legacy = dir_path().join(LEGACY_FILE);
+    debug!("Migrating \"{}\" to \"{}\"", legacy.display(), path().display());
+    let ini = Ini::load_from_file(&legacy).map_err(|error| match error {
+        ini::Error::Io(error) => error,
+        ini::Error::Parse(error) => IoError::new(ErrorKind::InvalidData, format!("invalid {}: {}", LEGACY_FILE, error)),
+    })?;
+
+    let mut store = SourceStore::default();
+    for (name, url) in ini.general_section().iter().filter(|(name, _)| *name != "local") {
+        let settings = ini.section(Some(name)).cloned().unwrap_or_default();
+        let get = |key: &str| settings.get(key).map(String::from);
+        let mut source = Source::new(name, url);
+        source.enabled = settings.get("enabled") != Some("false");
+        source.trusted = settings.get("trusted") == Some("true");
+        source.auth = settings.get("token_env").map(|variable| format!("env:{}", variable));
+        source.mirrors = settings.get("mirrors").map(|mirrors| mirrors.split_whitespace().map(String::from).collect()).unwrap_or_default();
+        source.public_key = get("public_key");
+        source.proxy = get("proxy");
+        source.ca_cert = get("ca_cert");
+        source.keep_versions = settings.get("keep_versions").and_then(|keep| keep.parse().ok());
+        source.upload_url = get("upload_url");
+
+        let marker = cache_path().join(name).join(LEGACY_LAST_SYNCED);
+        if let Ok(time) = fs::read_to_string(&marker) {
+            source.last_synced = Some(time.trim().to_string());
+            fs::remove_file(marker)?;
+        }
+        store.sources.push(source);
+    }
+
+    store.save()?;
+    return fs::rename(&legacy, legacy.with_extension("ini.bak"));
 }
 
 pub fn dir_path() -> PathBuf {
     let project_dirs = ProjectDirs::from("", "", built_info::PKG_NAME);
     return project_dirs.as_ref().unwrap().config_local_dir().to_path_buf();
 }
+
+// Synced templates can always be downloaded again, so they're kept in the
+// cache directory instead of next to the configuration
+pub fn cache_path() -> PathBuf {
+    let project_dirs = ProjectDirs::from("", "", built_info::PKG_NAME);
+    return project_dirs.as_ref().unwrap().cache_dir().to_path_buf();
+}
+
+// Versions of the templates of a source kept by sync, see
+// template::keep_versions
+fn versions_path(source: &str) -> PathBuf {
+    return cache_path().join(".versions").join(source);
+}
+
+pub fn templates_path(source: &str) -> PathBuf {
+    if source == "local" {
+        return Source::local().templates_path();
+    }
+
+    return match SourceStore::load().unwrap_or_default().get(source) {
+        Some(source) => source.templates_path(),
+        None => cache_path().join(source),
+    };
+}
+
+// Directory sources are given either as file:// URLs or as plain paths
+pub fn directory(url: &str) -> Option<PathBuf> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return Some(PathBuf::from(path));
+    } else if !url.contains("://") && !url.is_empty() && url != "LOCAL" {
+        return Some(PathBuf::from(url));
+    }
+
+    return None;
+}
+
+pub fn is_valid_url(url: &str) -> bool {
+    if let Some(path) = directory(url) {
+        return path.is_dir();
+    }
+
+    // SSH remotes (e.g. git+ssh://git@host/org/repo.git) don't look like web
+    // URLs at all, so only make sure there's something after the scheme
+    if let Some(remote) = url.strip_prefix("git+ssh://") {
+        return !remote.is_empty();
+    }
+
+    // Hosts on a LAN (like the ones `srcinit serve` runs on) are often plain
+    // IP addresses or names without a domain, which is_url doesn't take
+    let url = url.strip_prefix("git+").unwrap_or(url);
+    return is_url(url) || url.parse::<Uri>().is_ok_and(|uri| matches!(uri.scheme_str(), Some("http" | "https")) && uri.host().is_some_and(|host| !host.is_empty()));
+}
+
+// Directory sources are always stored as absolute file:// URLs, so they
+// keep working no matter where srcinit is run from
+pub fn normalize_url(url: &str) -> IoResult<String> {
+    return match directory(url) {
+        Some(path) => Ok(format!("file://{}", fs::canonicalize(path)?.display())),
+        None => Ok(url.to_string()),
+    };
+}
+
+pub fn is_git(url: &str) -> bool {
+    return url.starts_with("git+");
+}
+
+// The remote to clone when a template is given as a git URL (e.g.
+// https://github.com/org/template.git), to generate from it without adding
+// it as a source first
+pub fn git_remote(name: &str) -> Option<&str> {
+    if let Some(remote) = name.strip_prefix("git+") {
+        return Some(remote);
+    }
+
+    let is_remote = ["https://", "http://", "ssh://", "git://", "file://", "git@"].iter().any(|scheme| name.starts_with(scheme));
+    return (is_remote && name.trim_end_matches('/').ends_with(".git")).then_some(name);
+}
+
+// Shallow clone of a remote, without any of its history
+pub fn clone(remote: &str, path: &Path) -> IoResult<()> {
+    let mut command = Command::new("git");
+    command.args(["clone", "--depth", "1", "--quiet", remote]).arg(path);
+    return run_git(command);
+}
+
+// Credentials of a renamed source are kept in a section named after it
+fn rename_section(ini: &mut Ini, old: &str, new: &str) {
+    if let Some(properties) = ini.delete(Some(old)) {
+        for (key, value) in properties.iter() {
+            ini.with_section(Some(new)).set(key, value);
+        }
+    }
+}
+
+// Syncs every given source using up to `jobs` threads, reporting each
+// result as soon as that source is done
+pub fn sync_all(targets: Vec<Source>, jobs: usize, report: impl Fn(&str, IoResult<Synced>) + Sync) {
+    run_all(targets, jobs, sync, report);
+}
+
+pub fn check_all(targets: Vec<Source>, jobs: usize, report: impl Fn(&str, IoResult<Vec<Change>>) + Sync) {
+    run_all(targets, jobs, check, report);
+}
+
+// Runs a task for every source on a fixed number of worker threads, each
+// source getting its own progress bar while it runs. Results are reported as
+// soon as they're available, with the bars out of the way
+fn run_all<T>(
+    targets: Vec<Source>,
+    jobs: usize,
+    task: impl Fn(&Source, &ProgressBar) -> IoResult<T> + Sync,
+    report: impl Fn(&str, IoResult<T>) + Sync,
+) {
+    let queue = Mutex::new(targets.into_iter());
+    let workers = jobs.max(1);
+    let bars = MultiProgress::with_draw_target(progress::target());
+    let style = ProgressStyle::with_template("{spinner} {prefix:.bold} {bytes}/{total_bytes} ({bytes_per_sec})").unwrap();
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap().next();
+                let Some(source) = next else {
+                    break;
+                };
+
+                let progress = bars.add(ProgressBar::new(0).with_style(style.clone()).with_prefix(source.name.clone()));
+                progress.enable_steady_tick(Duration::from_millis(100));
+                let result = task(&source, &progress);
+                progress.finish_and_clear();
+                bars.suspend(|| report(&source.name, result));
+            });
+        }
+    });
+}
+
+pub struct Synced {
+    pub templates: usize,
+    // Where the templates actually came from, which for HTTP sources may be
+    // one of the mirrors instead of the source URL
+    pub url: String,
+    // False when the source reported nothing changed since the last sync
+    pub updated: bool,
+}
+
+pub fn sync(source: &Source, progress: &ProgressBar) -> IoResult<Synced> {
+    debug!("Syncing \"{}\" from {}", source.name, source.url);
+
+    // Versions of the templates are kept before syncing too, so the ones
+    // synced before versions were kept aren't lost to the update
+    let keep = source.keep_versions.unwrap_or(template::KEEP_VERSIONS);
+    template::keep_versions(&source.name, keep)?;
+
+    // Nothing to fetch for directory sources, as long as they still exist
+    if let Some(path) = directory(&source.url) {
+        if !path.is_dir() {
+            return Err(IoError::new(ErrorKind::NotFound, format!("directory \"{}\" does not exist", path.display())));
+        }
+        return Ok(Synced { templates: template::list(&source.name).len(), url: source.url.clone(), updated: true });
+    }
+
+    let (url, updated) = if source.kind == Kind::Git {
+        sync_git(source)?;
+        (source.url.clone(), true)
+    } else {
+        remote::sync(source, progress)?
+    };
+
+    record_synced(&source.name)?;
+    template::keep_versions(&source.name, keep)?;
+    return Ok(Synced { templates: template::list(&source.name).len(), url, updated });
+}
+
+// What syncing would change, without downloading any template. Directory
+// sources are read in place and so are never out of date
+pub fn check(source: &Source, progress: &ProgressBar) -> IoResult<Vec<Change>> {
+    return match source.kind {
+        Kind::Directory => Ok(Vec::new()),
+        Kind::Git => Err(IoError::new(ErrorKind::Unsupported, "git sources can't be checked for updates")),
+        Kind::Http => remote::check(source, progress),
+    };
+}
+
+// Copies every template of a source, as last synced, into a directory that
+// can be carried elsewhere (e.g. onto an air-gapped network) and added as a
+// directory source there. The index of HTTP sources comes along as it was
+// downloaded, to tell later where the templates came from
+pub fn mirror(source: &str, output: &Path) -> IoResult<usize> {
+    let output = path::absolute(output)?;
+    let name = output.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let partial = output.with_file_name(format!(".{}.mirror", name));
+    if partial.exists() {
+        fs::remove_dir_all(&partial)?;
+    }
+    fs::create_dir_all(&partial)?;
+
+    let templates = template::list(source);
+    for template in &templates {
+        template::copy_dir(&template.path, &partial.join(&template.name), None)?;
+    }
+    let index = templates_path(source).join(format!(".{}", remote::INDEX));
+    if index.is_file() {
+        fs::copy(index, partial.join(remote::INDEX))?;
+    }
+
+    // Only an empty directory can be there, the caller made sure of it
+    if output.exists() {
+        fs::remove_dir(&output)?;
+    }
+    fs::rename(partial, output)?;
+    return Ok(templates.len());
+}
+
+// Sources are synced on several threads at once, each of them writing the
+// sources file when done
+fn record_synced(source: &str) -> IoResult<()> {
+    static WRITING: Mutex<()> = Mutex::new(());
+    let _writing = WRITING.lock().unwrap();
+    let mut store = SourceStore::load()?;
+    if let Ok(source) = store.get_mut(source) {
+        source.last_synced = Some(Utc::now().to_rfc3339());
+        store.save()?;
+    }
+    return Ok(());
+}
+
+// The commit a git source is currently synced to
+pub fn revision(source: &str) -> Option<String> {
+    let path = templates_path(source);
+    if !path.join(".git").is_dir() {
+        return None;
+    }
+
+    let output = Command::new("git").arg("-C").arg(&path).args(["rev-parse", "HEAD"]).output().ok()?;
+    return output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string());
+}
+
+// Git sources are cloned straight into their templates directory, with
+// every top-level directory of the repository being a template
+fn sync_git(source: &Source) -> IoResult<()> {
+    let path = source.templates_path();
+    let remote = source.url.strip_prefix("git+").unwrap();
+
+    let mut command = git(source)?;
+    if path.join(".git").is_dir() {
+        command.arg("-C").arg(&path).args(["pull", "--ff-only", "--quiet"]);
+    } else {
+        // Whatever is there wasn't cloned by us (e.g. the source URL used to
+        // point somewhere else), so start over from a fresh clone
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::create_dir_all(path.parent().unwrap())?;
+        command.args(["clone", "--depth", "1", "--quiet", remote]).arg(&path);
+    }
+
+    return run_git(command);
+}
+
+// A git command honoring the settings of a source. git already honors the
+// proxy environment variables by itself, only a per-source proxy has to be
+// handed over (an empty one disables proxying)
+pub fn git(source: &Source) -> IoResult<Command> {
+    let mut command = Command::new("git");
+    if let Some(proxy) = &source.proxy {
+        command.arg("-c").arg(format!("http.proxy={}", if proxy == "none" { "" } else { proxy }));
+    }
+    if let Some(bundle) = remote::ca_bundle(source)? {
+        command.arg("-c").arg(format!("http.sslCAInfo={}", bundle.display()));
+    }
+
+    return Ok(command);
+}
+
+pub fn run_git(mut command: Command) -> IoResult<()> {
+    debug!("Running {:?}", command);
+    let output = command.output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().find(|line| !line.trim().is_empty()).unwrap_or("git failed");
+        return Err(IoError::other(reason.trim().to_string()));
+    }
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_to_change_local() {
+        let mut store = SourceStore::default();
+        let refused = |result: IoResult<()>| result.err().map(|error| error.kind()) == Some(ErrorKind::InvalidInput);
+        assert!(refused(store.edit("local", "https://example.com/templates").map(|_| ())));
+        assert!(refused(store.rename("local", "mine")));
+        assert!(refused(store.remove("local").map(|_| ())));
+        assert!(refused(store.set_enabled("local", false)));
+        assert!(refused(store.set_trusted("local", false)));
+        assert_eq!(store.get("local").unwrap().url, "LOCAL");
+    }
+}
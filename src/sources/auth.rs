@@ -0,0 +1,95 @@
+// Credentials never go into sources.toml. A source can either reference an
+// environment variable holding a bearer token (`auth = "env:VARIABLE"` in its entry),
+// have a token stored in the OS keyring through `srcinit source-login`, or
+// have its credentials in a separate credentials.ini next to sources.toml:
+//
+//   [corp-templates]
+//   token=...
+//
+//   [other-templates]
+//   username=...
+//   password=...
+use crate::{built_info, sources::{dir_path, Source}};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ini::Ini;
+use keyring::Entry;
+use std::{env, io::{Error as IoError, ErrorKind, Result as IoResult}, path::PathBuf};
+
+pub enum Auth {
+    Bearer(String),
+    Basic(String, String),
+}
+
+impl Auth {
+    pub fn header(&self) -> String {
+        return match self {
+            Auth::Bearer(token) => format!("Bearer {}", token),
+            Auth::Basic(username, password) => format!("Basic {}", STANDARD.encode(format!("{}:{}", username, password))),
+        };
+    }
+}
+
+pub fn path() -> PathBuf {
+    return dir_path().join("credentials.ini");
+}
+
+pub fn resolve(source: &Source) -> IoResult<Option<Auth>> {
+    if let Some(variable) = source.token_env() {
+        return match env::var(variable) {
+            Ok(token) => Ok(Some(Auth::Bearer(token))),
+            Err(..) => Err(IoError::new(ErrorKind::NotFound, format!("environment variable {} is not set", variable))),
+        };
+    }
+
+    // A keyring that can't be reached (e.g. no secret service running) is
+    // treated like an empty one, so the credentials file still gets a say
+    if let Some(token) = keyring_entry(&source.name).ok().and_then(|entry| entry.get_password().ok()) {
+        return Ok(Some(Auth::Bearer(token)));
+    }
+
+    let path = path();
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let credentials = Ini::load_from_file(path).map_err(|error| IoError::new(ErrorKind::InvalidData, error.to_string()))?;
+    let Some(section) = credentials.section(Some(&source.name)) else {
+        return Ok(None);
+    };
+
+    if let Some(token) = section.get("token") {
+        return Ok(Some(Auth::Bearer(token.to_string())));
+    } else if let (Some(username), Some(password)) = (section.get("username"), section.get("password")) {
+        return Ok(Some(Auth::Basic(username.to_string(), password.to_string())));
+    }
+
+    return Ok(None);
+}
+
+pub fn keyring_entry(source: &str) -> IoResult<Entry> {
+    return Entry::new(built_info::PKG_NAME, source).map_err(IoError::other);
+}
+
+pub fn login(source: &str, token: &str) -> IoResult<()> {
+    return keyring_entry(source)?.set_password(token).map_err(IoError::other);
+}
+
+pub fn logout(source: &str) -> IoResult<()> {
+    return match keyring_entry(source)?.delete_credential() {
+        Ok(()) => Ok(()),
+        Err(keyring::Error::NoEntry) => Err(IoError::new(ErrorKind::NotFound, "not logged in")),
+        Err(error) => Err(IoError::other(error)),
+    };
+}
+
+// Credentials are only ever sent back to the host of the source itself, so
+// templates hosted elsewhere (e.g. on a CDN) never get to see them
+pub fn same_origin(a: &str, b: &str) -> bool {
+    let origin = |url: &str| {
+        let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+        let host = rest.split('/').next().unwrap_or("");
+        format!("{}://{}", scheme.to_lowercase(), host.to_lowercase())
+    };
+
+    return origin(a) == origin(b);
+}
@@ -0,0 +1,603 @@
+// HTTP sources are plain static file hosts. The source URL points at a
+// directory containing an "index.json" document (see README.md for the
+// full specification) which lists every template the source provides
+use crate::sources::{auth::{self, Auth}, cache_path, Source};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::read::GzDecoder;
+use indicatif::ProgressBar;
+use log::{debug, trace};
+use ini::{Ini, Properties};
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    env,
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs::{self, File, OpenOptions},
+    io::{self, Error as IoError, ErrorKind, Read, Result as IoResult},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU32, Ordering},
+    thread,
+    time::Duration,
+};
+use tar::Archive;
+use ureq::{
+    http::{Response, StatusCode, Uri},
+    tls::{parse_pem, Certificate, PemItem, RootCerts, TlsConfig},
+    Agent, Body, Proxy,
+};
+
+pub const INDEX: &str = "index.json";
+pub const FORMAT_VERSION: u32 = 1;
+// Where the ETag/Last-Modified of the last index fetch are kept
+const VALIDATORS: &str = ".validators.ini";
+// Where interrupted downloads are kept (by source, named after their
+// checksum) until the next sync resumes them. Sources serving the same
+// archive each get their own, since they may be synced at the same time
+const PARTIAL: &str = ".partial";
+
+// How many times a failing request is retried, set once from the command line
+static RETRIES: AtomicU32 = AtomicU32::new(3);
+
+// The raw index document, its parsed form and the validators it came with
+type Fetched = (Vec<u8>, Index, Properties);
+
+#[derive(Deserialize, Serialize)]
+pub struct Index {
+    pub version: u32,
+    #[serde(default)]
+    pub templates: Vec<Entry>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Entry {
+    pub name: String,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub url: String,
+    pub sha256: String,
+}
+
+pub fn parse(data: &[u8]) -> IoResult<Index> {
+    let invalid = |message: String| IoError::new(ErrorKind::InvalidData, format!("invalid {}: {}", INDEX, message));
+    let index: Index = serde_json::from_slice(data).map_err(|error| invalid(error.to_string()))?;
+    if index.version != FORMAT_VERSION {
+        return Err(invalid(format!("unsupported format version {}", index.version)));
+    }
+
+    // Template names become directory names, so anything that could escape
+    // the cache directory (or collide with our own hidden files) is rejected
+    let mut names = Vec::new();
+    for entry in &index.templates {
+        let name = entry.name.as_str();
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(invalid(format!("invalid template name \"{}\"", name)));
+        } else if names.contains(&name) {
+            return Err(invalid(format!("duplicate template \"{}\"", name)));
+        } else if entry.sha256.len() != 64 || !entry.sha256.chars().all(|char| char.is_ascii_hexdigit()) {
+            return Err(invalid(format!("invalid checksum for template \"{}\"", name)));
+        }
+        names.push(name);
+    }
+
+    return Ok(index);
+}
+
+// Download URLs in the index may be absolute, or relative to the source URL
+pub fn resolve(base: &str, url: &str) -> String {
+    if url.contains("://") {
+        return url.to_string();
+    }
+
+    return format!("{}/{}", base.trim_end_matches('/'), url.trim_start_matches('/'));
+}
+
+// Fetches and parses the index of a source, verifying its signature first
+// whenever the source has a public key on record. Given the validators
+// (ETag/Last-Modified) of a previous fetch, nothing is returned when the
+// index hasn't changed since then
+pub fn fetch_index(url: &str, source: &Source, auth: Option<&Auth>, validators: Option<&Properties>, progress: &ProgressBar) -> IoResult<Option<Fetched>> {
+    let mut headers = Vec::new();
+    if let Some(validators) = validators {
+        if let Some(etag) = validators.get("etag") {
+            headers.push(("If-None-Match", etag));
+        }
+        if let Some(last_modified) = validators.get("last_modified") {
+            headers.push(("If-Modified-Since", last_modified));
+        }
+    }
+
+    let mut response = request(&resolve(url, INDEX), source, auth, &headers)?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        debug!("Index of {} not modified since the last sync", url);
+        return Ok(None);
+    }
+
+    let mut validators = Properties::new();
+    validators.insert("url", url);
+    for (header, key) in [("ETag", "etag"), ("Last-Modified", "last_modified")] {
+        if let Some(value) = response.headers().get(header).and_then(|value| value.to_str().ok()) {
+            validators.insert(key, value);
+        }
+    }
+
+    let data = read(&mut response, progress)?;
+    if let Some(public_key) = &source.public_key {
+        debug!("Verifying the index signature of {}", url);
+        let signature = fetch(&resolve(url, &format!("{}.minisig", INDEX)), source, auth, progress)?;
+        verify(&data, &signature, public_key)?;
+    }
+
+    let index = parse(&data)?;
+    return Ok(Some((data, index, validators)));
+}
+
+// Tries the source URL and then each of its mirrors in order, moving on to
+// the next one only when a server is unreachable or failing (timeouts, 5xx).
+// Returns the URL that was used, and whether anything changed
+pub fn sync(source: &Source, progress: &ProgressBar) -> IoResult<(String, bool)> {
+    let auth = auth::resolve(source)?;
+    let (url, fetched) = fetch_any(source, auth.as_ref(), progress)?;
+    let Some((data, index, validators)) = fetched else {
+        return Ok((url, false));
+    };
+
+    // Templates are unpacked into a staging directory first so a failed sync
+    // never leaves the previously synced templates half-replaced
+    let staging = cache_path().join(format!(".{}.sync", source.name));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging)?;
+
+    for entry in &index.templates {
+        let download = resolve(&url, &entry.url);
+        debug!("Downloading template \"{}\" from {}", entry.name, download);
+        let partial = cache_path().join(PARTIAL).join(&source.name).join(entry.sha256.to_ascii_lowercase());
+        let payload = fetch_resumable(&download, &partial, source, auth.as_ref().filter(|_| auth::same_origin(&url, &download)), progress)?;
+        fs::remove_file(&partial)?;
+        if !sha256(&payload).eq_ignore_ascii_case(&entry.sha256) {
+            return Err(IoError::new(ErrorKind::InvalidData, format!("checksum mismatch for template \"{}\"", entry.name)));
+        }
+        Archive::new(GzDecoder::new(payload.as_slice())).unpack(staging.join(&entry.name))?;
+    }
+
+    // The index is kept around so the cache can later be compared against it
+    fs::write(staging.join(format!(".{}", INDEX)), data)?;
+    let mut ini = Ini::new();
+    *ini.general_section_mut() = validators;
+    ini.write_to_file(staging.join(VALIDATORS))?;
+
+    let path = source.templates_path();
+    if path.exists() {
+        fs::remove_dir_all(&path)?;
+    }
+    fs::rename(staging, path)?;
+    return Ok((url, true));
+}
+
+fn fetch_any(source: &Source, auth: Option<&Auth>, progress: &ProgressBar) -> IoResult<(String, Option<Fetched>)> {
+    // Validators only mean something to the server that handed them out
+    let previous = Ini::load_from_file(source.templates_path().join(VALIDATORS)).ok();
+    let previous = previous.as_ref().map(|ini| ini.general_section());
+
+    let mut result = Err(IoError::new(ErrorKind::NotFound, "no URL to sync from"));
+    for url in source.urls() {
+        let validators = previous.filter(|validators| validators.get("url") == Some(url.as_str()));
+        result = fetch_index(&url, source, auth, validators, progress).map(|fetched| (url.clone(), fetched));
+        match &result {
+            Err(error) if is_unavailable(error) => {
+                debug!("{} is unavailable ({}), trying the next mirror", url, error);
+                continue;
+            }
+            _ => break,
+        }
+    }
+
+    return result;
+}
+
+pub enum Change {
+    Added { name: String, version: Option<String> },
+    Updated { name: String, from: Option<String>, to: Option<String> },
+    Removed { name: String },
+}
+
+// Compares the remote index against the one kept from the last sync without
+// downloading any template. A source that was never synced reports every
+// template as added
+pub fn check(source: &Source, progress: &ProgressBar) -> IoResult<Vec<Change>> {
+    let auth = auth::resolve(source)?;
+    let (_, fetched) = fetch_any(source, auth.as_ref(), progress)?;
+    let Some((_, index, _)) = fetched else {
+        return Ok(Vec::new());
+    };
+
+    let cached = fs::read(source.templates_path().join(format!(".{}", INDEX)))
+        .ok()
+        .and_then(|data| parse(&data).ok())
+        .map(|index| index.templates)
+        .unwrap_or_default();
+
+    let mut changes = Vec::new();
+    for entry in &index.templates {
+        match cached.iter().find(|cached| cached.name == entry.name) {
+            None => changes.push(Change::Added { name: entry.name.clone(), version: entry.version.clone() }),
+            Some(cached) if !cached.sha256.eq_ignore_ascii_case(&entry.sha256) => changes.push(Change::Updated {
+                name: entry.name.clone(),
+                from: cached.version.clone(),
+                to: entry.version.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for cached in &cached {
+        if !index.templates.iter().any(|entry| entry.name == cached.name) {
+            changes.push(Change::Removed { name: cached.name.clone() });
+        }
+    }
+
+    return Ok(changes);
+}
+
+// Whether an error came from talking to a server at all, as opposed to
+// something local (the disk, a bad index...)
+pub fn is_network(error: &IoError) -> bool {
+    let inner = error.get_ref();
+    if let Some(exhausted) = inner.and_then(|error| error.downcast_ref::<Exhausted>()) {
+        return is_network(&exhausted.error);
+    }
+
+    return inner.is_some_and(|error| error.is::<ureq::Error>());
+}
+
+// Whether an error means the server itself couldn't be used, as opposed to
+// it answering with something we don't like (a 404, a bad checksum...)
+pub fn is_unavailable(error: &IoError) -> bool {
+    let inner = error.get_ref();
+    if let Some(exhausted) = inner.and_then(|error| error.downcast_ref::<Exhausted>()) {
+        return is_unavailable(&exhausted.error);
+    }
+
+    let Some(error) = inner.and_then(|error| error.downcast_ref::<ureq::Error>()) else {
+        return false;
+    };
+
+    return match error {
+        ureq::Error::StatusCode(status) => *status >= 500,
+        ureq::Error::Timeout(..)
+        | ureq::Error::Io(..)
+        | ureq::Error::HostNotFound
+        | ureq::Error::ConnectionFailed => true,
+        _ => false,
+    };
+}
+
+fn verify(data: &[u8], signature: &[u8], public_key: &str) -> IoResult<()> {
+    let invalid = |error: minisign_verify::Error| IoError::new(ErrorKind::InvalidData, format!("index signature: {}", error));
+    let public_key = PublicKey::from_base64(public_key).map_err(invalid)?;
+    let signature = Signature::decode(&String::from_utf8_lossy(signature)).map_err(invalid)?;
+    return public_key.verify(data, &signature, false).map_err(invalid);
+}
+
+pub fn sha256(data: &[u8]) -> String {
+    return Sha256::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect();
+}
+
+// Without a connect timeout, an unreachable mirror would block forever
+// instead of letting the next one take over
+fn agent(url: &str, source: &Source) -> IoResult<Agent> {
+    let mut config = Agent::config_builder()
+        .timeout_connect(Some(Duration::from_secs(10)))
+        .proxy(proxy(url, source)?);
+
+    // A source's own CA is trusted on top of the bundled roots, never
+    // instead of them, so mirrors and download hosts elsewhere still work
+    if let Some(path) = &source.ca_cert {
+        let mut roots: Vec<Certificate<'static>> = webpki_root_certs::TLS_SERVER_ROOT_CERTS
+            .iter()
+            .map(|certificate| Certificate::from_der(certificate.as_ref()))
+            .collect();
+        roots.extend(certificates(Path::new(path))?);
+        config = config.tls_config(TlsConfig::builder().root_certs(RootCerts::from(roots)).build());
+    }
+
+    return Ok(config.build().into());
+}
+
+// git can only be handed a whole bundle of CAs, which it then trusts instead
+// of its own, so a source's CA is bundled with the same roots HTTP sources
+// trust (and rewritten every time, in case the CA file changed)
+pub fn ca_bundle(source: &Source) -> IoResult<Option<PathBuf>> {
+    let Some(path) = &source.ca_cert else {
+        return Ok(None);
+    };
+
+    let roots = webpki_root_certs::TLS_SERVER_ROOT_CERTS.iter().map(|certificate| certificate.as_ref().to_vec());
+    let own = certificates(Path::new(path))?.into_iter().map(|certificate| certificate.der().to_vec());
+    let file = cache_path().join(".certificates").join(format!("{}.pem", source.name));
+    fs::create_dir_all(file.parent().unwrap())?;
+    let partial = file.with_extension("pem.partial");
+    fs::write(&partial, pem(roots.chain(own)))?;
+    fs::rename(partial, &file)?;
+    return Ok(Some(file));
+}
+
+fn pem(certificates: impl Iterator<Item = Vec<u8>>) -> String {
+    let mut bundle = String::new();
+    for certificate in certificates {
+        bundle.push_str("-----BEGIN CERTIFICATE-----\n");
+        for line in STANDARD.encode(certificate).as_bytes().chunks(64) {
+            bundle.push_str(&String::from_utf8_lossy(line));
+            bundle.push('\n');
+        }
+        bundle.push_str("-----END CERTIFICATE-----\n");
+    }
+    return bundle;
+}
+
+// Reads the certificates of a PEM bundle, or a single DER encoded one
+pub fn certificates(path: &Path) -> IoResult<Vec<Certificate<'static>>> {
+    let invalid = |reason: &str| IoError::new(ErrorKind::InvalidData, format!("CA certificate \"{}\": {}", path.display(), reason));
+    let data = fs::read(path).map_err(|error| invalid(&error.to_string()))?;
+    if !data.starts_with(b"-----") {
+        return Ok(vec![Certificate::from_der(&data).to_owned()]);
+    }
+
+    let mut certificates = Vec::new();
+    for item in parse_pem(&data) {
+        if let PemItem::Certificate(certificate) = item.map_err(|error| invalid(&error.to_string()))? {
+            certificates.push(certificate);
+        }
+    }
+    if certificates.is_empty() {
+        return Err(invalid("no certificate found"));
+    }
+
+    return Ok(certificates);
+}
+
+// A source may name its own proxy ("none" to always connect directly),
+// otherwise the usual HTTP_PROXY/HTTPS_PROXY/ALL_PROXY and NO_PROXY
+// environment variables apply
+pub fn proxy(url: &str, source: &Source) -> IoResult<Option<Proxy>> {
+    let invalid = |proxy: &str| IoError::new(ErrorKind::InvalidInput, format!("invalid proxy \"{}\"", proxy));
+    if let Some(proxy) = &source.proxy {
+        if proxy == "none" {
+            return Ok(None);
+        }
+        return Proxy::new(proxy).map(Some).map_err(|_| invalid(proxy));
+    }
+
+    let host = url.parse::<Uri>().ok().and_then(|uri| uri.host().map(str::to_string)).unwrap_or_default();
+    if is_no_proxy(&host) {
+        return Ok(None);
+    }
+
+    let names = match url.starts_with("https://") {
+        true => ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"],
+        false => ["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"],
+    };
+    let Some(proxy) = names.iter().find_map(|name| env::var(name).ok().filter(|value| !value.trim().is_empty())) else {
+        return Ok(None);
+    };
+    return Proxy::new(proxy.trim()).map(Some).map_err(|_| invalid(&proxy));
+}
+
+// NO_PROXY is a comma separated list of hosts, each also matching its
+// subdomains (a leading "." or "*." is optional), or "*" for every host
+fn is_no_proxy(host: &str) -> bool {
+    let Some(list) = ["NO_PROXY", "no_proxy"].iter().find_map(|name| env::var(name).ok()) else {
+        return false;
+    };
+
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase();
+    return list.split(',').map(str::trim).filter(|entry| !entry.is_empty()).any(|entry| {
+        if entry == "*" {
+            return true;
+        }
+
+        let entry = entry.trim_start_matches('*').trim_start_matches('.').to_ascii_lowercase();
+        return host == entry || host.ends_with(&format!(".{}", entry));
+    });
+}
+
+pub fn set_retries(retries: u32) {
+    RETRIES.store(retries, Ordering::Relaxed);
+}
+
+// Transient failures (see is_unavailable) are retried with an exponential,
+// jittered backoff before giving up on a URL
+fn request(url: &str, source: &Source, auth: Option<&Auth>, headers: &[(&str, &str)]) -> IoResult<Response<Body>> {
+    let agent = agent(url, source)?;
+    let attempts = RETRIES.load(Ordering::Relaxed) + 1;
+    let mut attempt = 1;
+
+    loop {
+        let mut request = agent.get(url);
+        if let Some(auth) = auth {
+            request = request.header("Authorization", auth.header());
+        }
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+
+        debug!("GET {}", url);
+        trace!("Request headers: {:?}", headers);
+        let error = match request.call() {
+            Ok(response) => {
+                trace!("{} answered {} with {:?}", url, response.status(), response.headers());
+                return Ok(response);
+            }
+            Err(error) => IoError::other(error),
+        };
+        if !is_unavailable(&error) {
+            return Err(error);
+        } else if attempt >= attempts {
+            return Err(IoError::other(Exhausted { attempts, error }));
+        }
+
+        let backoff = 500u64.saturating_mul(1 << (attempt - 1).min(4));
+        let jitter = fastrand::u64(0..=backoff / 2);
+        debug!("GET {} failed ({}), retrying in {}ms (attempt {} of {})", url, error, backoff / 2 + jitter, attempt + 1, attempts);
+        thread::sleep(Duration::from_millis(backoff / 2 + jitter));
+        attempt += 1;
+    }
+}
+
+#[derive(Debug)]
+struct Exhausted {
+    attempts: u32,
+    error: IoError,
+}
+
+impl Display for Exhausted {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        if self.attempts == 1 {
+            return write!(formatter, "{}", self.error);
+        }
+
+        return write!(formatter, "gave up after {} attempts: {}", self.attempts, self.error);
+    }
+}
+
+impl StdError for Exhausted {}
+
+pub fn fetch(url: &str, source: &Source, auth: Option<&Auth>, progress: &ProgressBar) -> IoResult<Vec<u8>> {
+    let mut response = request(url, source, auth, &[])?;
+    return read(&mut response, progress);
+}
+
+// Sends a packed template to the upload endpoint of a source, which is then
+// up to the server to add to its index. Uploads are never retried, a server
+// may well have taken one that timed out
+pub fn upload(url: &str, source: &Source, auth: Option<&Auth>, data: &[u8]) -> IoResult<()> {
+    let mut request = agent(url, source)?.put(url).header("Content-Type", "application/gzip");
+    if let Some(auth) = auth {
+        request = request.header("Authorization", auth.header());
+    }
+
+    debug!("PUT {} ({} bytes)", url, data.len());
+    let response = request.send(data).map_err(IoError::other)?;
+    trace!("{} answered {} with {:?}", url, response.status(), response.headers());
+    return Ok(());
+}
+
+// Downloads into a partial file first, so that when the transfer breaks off
+// the next attempt can ask for just the missing bytes. Servers that don't
+// support ranges simply send everything again
+fn fetch_resumable(url: &str, partial: &Path, source: &Source, auth: Option<&Auth>, progress: &ProgressBar) -> IoResult<Vec<u8>> {
+    fs::create_dir_all(partial.parent().unwrap())?;
+    let offset = fs::metadata(partial).map(|metadata| metadata.len()).unwrap_or(0);
+    let range = format!("bytes={}-", offset);
+    let headers = if offset > 0 { vec![("Range", range.as_str())] } else { Vec::new() };
+    if offset > 0 {
+        debug!("Resuming {} from byte {}", url, offset);
+    }
+
+    let mut response = match request(url, source, auth, &headers) {
+        // Whatever is left over doesn't match the file anymore
+        Err(error) if offset > 0 && is_status(&error, StatusCode::RANGE_NOT_SATISFIABLE) => {
+            fs::remove_file(partial)?;
+            return fetch_resumable(url, partial, source, auth, progress);
+        }
+        result => result?,
+    };
+
+    let mut file = if response.status() == StatusCode::PARTIAL_CONTENT {
+        progress.inc_length(offset);
+        progress.inc(offset);
+        OpenOptions::new().append(true).open(partial)?
+    } else {
+        File::create(partial)?
+    };
+    if let Some(length) = response.body().content_length() {
+        progress.inc_length(length);
+    }
+    io::copy(&mut progress.wrap_read(response.body_mut().as_reader()), &mut file)?;
+    drop(file);
+
+    return fs::read(partial);
+}
+
+pub fn is_status(error: &IoError, status: StatusCode) -> bool {
+    return matches!(
+        error.get_ref().and_then(|error| error.downcast_ref::<ureq::Error>()),
+        Some(ureq::Error::StatusCode(code)) if *code == status.as_u16()
+    );
+}
+
+// Reads a whole response body, counting its bytes on the progress bar
+fn read(response: &mut Response<Body>, progress: &ProgressBar) -> IoResult<Vec<u8>> {
+    if let Some(length) = response.body().content_length() {
+        progress.inc_length(length);
+    }
+
+    let mut data = Vec::new();
+    progress.wrap_read(response.body_mut().as_reader()).read_to_end(&mut data)?;
+    return Ok(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(templates: &str) -> String {
+        return format!("{{\"version\": {}, \"templates\": [{}]}}", FORMAT_VERSION, templates);
+    }
+
+    fn entry(name: &str, sha256: &str) -> String {
+        return format!("{{\"name\": \"{}\", \"url\": \"{}.tar.gz\", \"sha256\": \"{}\"}}", name, name, sha256);
+    }
+
+    #[test]
+    fn parses_indexes() {
+        let checksum = sha256(b"template");
+        let parsed = parse(index(&format!("{}, {}", entry("rust-cli", &checksum), entry("go", &checksum))).as_bytes()).unwrap();
+        let names: Vec<&str> = parsed.templates.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["rust-cli", "go"]);
+        assert_eq!(parsed.templates[0].url, "rust-cli.tar.gz");
+        assert!(parse(b"{\"version\": 1}").unwrap().templates.is_empty());
+
+        let invalid = |data: String| parse(data.as_bytes()).err().map(|error| error.kind());
+        assert_eq!(invalid(String::from("{\"version\": 2, \"templates\": []}")), Some(ErrorKind::InvalidData));
+        assert_eq!(invalid(String::from("not json")), Some(ErrorKind::InvalidData));
+        assert_eq!(invalid(index(&format!("{}, {}", entry("go", &checksum), entry("go", &checksum)))), Some(ErrorKind::InvalidData));
+        for name in ["", ".hidden", "../escape", "a\\\\b"] {
+            assert_eq!(invalid(index(&entry(name, &checksum))), Some(ErrorKind::InvalidData), "{}", name);
+        }
+    }
+
+    #[test]
+    fn resolves_download_urls() {
+        assert_eq!(resolve("https://example.com/templates/", "/go.tar.gz"), "https://example.com/templates/go.tar.gz");
+        assert_eq!(resolve("https://example.com/templates", "go.tar.gz"), "https://example.com/templates/go.tar.gz");
+        assert_eq!(resolve("https://example.com/templates", "https://cdn.example.com/go.tar.gz"), "https://cdn.example.com/go.tar.gz");
+    }
+
+    #[test]
+    fn checks_checksums() {
+        assert_eq!(sha256(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(sha256(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        // Checksums are compared ignoring case, but have to be whole
+        let checksum = sha256(b"template").to_ascii_uppercase();
+        assert!(parse(index(&entry("go", &checksum)).as_bytes()).is_ok());
+        for checksum in [&checksum[1..], "", &format!("G{}", &checksum[1..])] {
+            assert!(parse(index(&entry("go", checksum)).as_bytes()).is_err(), "{}", checksum);
+        }
+    }
+
+    #[test]
+    fn bundles_certificates_as_pem() {
+        let roots: Vec<Vec<u8>> = webpki_root_certs::TLS_SERVER_ROOT_CERTS.iter().take(3).map(|certificate| certificate.as_ref().to_vec()).collect();
+        let bundle = pem(roots.clone().into_iter());
+        assert!(bundle.lines().all(|line| line.len() <= 64));
+        let parsed: Vec<Vec<u8>> = parse_pem(bundle.as_bytes()).map(|item| match item.unwrap() {
+            PemItem::Certificate(certificate) => certificate.der().to_vec(),
+            _ => panic!("not a certificate"),
+        }).collect();
+        assert_eq!(parsed, roots);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{publish, scaffold, sources::{self, remote::Change, Source, SourceStore, Synced}, template::{self, Template}};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use semver::Version;
+use std::{io::{Error as IoError, ErrorKind, Result as IoResult}, path::{Path, PathBuf}};
+
+/// The configured sources of the current user and the templates they provide.
+///
+/// The store is a snapshot of the sources file taken when it's opened;
+/// changes made through [`TemplateStore::sources_mut`] only take effect once
+/// [`TemplateStore::save`] is called.
+pub struct TemplateStore {
+    sources: SourceStore,
+}
+
+impl TemplateStore {
+    /// Opens the store, starting from the default sources (just "local")
+    /// when none were configured yet.
+    pub fn open() -> IoResult<TemplateStore> {
+        return Ok(TemplateStore { sources: SourceStore::load()? });
+    }
+
+    /// Wraps already loaded sources.
+    pub fn from_sources(sources: SourceStore) -> TemplateStore {
+        return TemplateStore { sources };
+    }
+
+    /// Every source, in the order they were declared.
+    pub fn sources(&self) -> &SourceStore {
+        return &self.sources;
+    }
+
+    pub fn sources_mut(&mut self) -> &mut SourceStore {
+        return &mut self.sources;
+    }
+
+    /// Writes the sources file back to disk.
+    pub fn save(self) -> IoResult<()> {
+        return self.sources.save();
+    }
+
+    pub fn contains(&self, source: &str) -> bool {
+        return self.sources.contains(source);
+    }
+
+    pub fn is_enabled(&self, source: &str) -> bool {
+        return self.sources.get(source).is_some_and(|source| source.enabled);
+    }
+
+    /// Whether hooks of the source's templates run without asking first.
+    pub fn is_trusted(&self, source: &str) -> bool {
+        return self.sources.get(source).is_some_and(Source::is_trusted);
+    }
+
+    /// Whether templates of a source are available without syncing first.
+    pub fn is_cached(&self, source: &str) -> bool {
+        return self.sources.get(source).is_some_and(|source| source.is_local() || source.templates_path().is_dir());
+    }
+
+    /// The templates a source currently provides, sorted by name.
+    pub fn templates(&self, source: &str) -> Vec<Template> {
+        return template::list(source);
+    }
+
+    /// Other sources that templates of the source extend or depend on
+    /// templates of, but which aren't synced yet.
+    pub fn missing_sources(&self, source: &str) -> Vec<String> {
+        let mut missing: Vec<String> = Vec::new();
+        for template in self.templates(source) {
+            let manifest = &template.manifest;
+            for name in manifest.dependencies.keys().chain(&manifest.extends) {
+                // Invalid names are reported when generating, they can't
+                // point at a source's templates directory anyway
+                let Some((other, name)) = name.split_once('/').filter(|(other, name)| template::is_valid_name(other) && template::is_valid_name(name)) else {
+                    continue;
+                };
+                let is_missing = self.sources.get(other).is_some_and(|other| !other.templates_path().join(name).is_dir());
+                if other != source && is_missing && !missing.iter().any(|source| source == other) {
+                    missing.push(other.to_string());
+                }
+            }
+        }
+
+        return missing;
+    }
+
+    /// Looks a template up by name. A "source/template" name only looks in
+    /// that source, otherwise the local source is searched first and then
+    /// every enabled source in declaration order.
+    pub fn find(&self, name: &str) -> IoResult<Template> {
+        return template::find_in(&self.sources, name);
+    }
+
+    /// Templates of every enabled source matching `query`, best match first.
+    ///
+    /// Names, descriptions and tags are matched fuzzily (so "rcli" finds
+    /// "rust-cli"), with matches on the name counting twice as much.
+    pub fn search(&self, query: &str) -> Vec<Template> {
+        let matcher = SkimMatcherV2::default().ignore_case();
+        let score = |text: &str| matcher.fuzzy_match(text, query);
+
+        let mut found: Vec<(i64, Template)> = self.sources.iter()
+            .filter(|source| source.enabled)
+            .flat_map(|source| self.templates(&source.name))
+            .filter_map(|template| {
+                let manifest = &template.manifest;
+                let best = [
+                    score(&template.name).map(|score| score * 2),
+                    manifest.description.as_deref().and_then(score),
+                    manifest.tags.iter().filter_map(|tag| score(tag)).max(),
+                ].into_iter().flatten().max()?;
+                return Some((best, template));
+            })
+            .collect();
+
+        found.sort_by(|(a, first), (b, second)| b.cmp(a).then(first.qualified_name().cmp(&second.qualified_name())));
+        return found.into_iter().map(|(_, template)| template).collect();
+    }
+
+    /// Copies a template directory or archive into the local source.
+    pub fn import(&self, file: &Path) -> IoResult<Template> {
+        return template::import(file);
+    }
+
+    /// Creates a starter template, in the local source unless another
+    /// directory is given.
+    pub fn scaffold(&self, name: &str, dir: Option<&Path>) -> IoResult<PathBuf> {
+        return scaffold::scaffold(name, &dir.map(Path::to_path_buf).unwrap_or(sources::templates_path("local")));
+    }
+
+    /// Packs any template into `<output>/<name>.tar.gz`.
+    pub fn export(&self, name: &str, output: &Path) -> IoResult<PathBuf> {
+        return template::export(name, output);
+    }
+
+    /// Publishes the template in `root` to one of the sources, returning the
+    /// version published.
+    pub fn publish(&self, root: &Path, source: &str) -> IoResult<Version> {
+        let Some(source) = self.sources.get(source) else {
+            return Err(IoError::new(ErrorKind::NotFound, format!("source \"{}\" does not exist", source)));
+        };
+
+        return publish::publish(root, source);
+    }
+
+    /// Copies the synced templates of a source into a directory, which can
+    /// be added as a directory source elsewhere. Returns how many there were.
+    pub fn mirror(&self, source: &str, output: &Path) -> IoResult<usize> {
+        if !self.is_cached(source) {
+            return Err(IoError::new(ErrorKind::NotFound, format!("source \"{}\" isn't synced yet", source)));
+        }
+
+        return sources::mirror(source, output);
+    }
+
+    /// The sources a sync would go through: every enabled one but "local",
+    /// or just `only` when given.
+    pub fn sync_targets(&self, only: Option<&str>) -> IoResult<Vec<Source>> {
+        if let Some(only) = only {
+            if only == "local" {
+                return Err(IoError::new(ErrorKind::InvalidInput, "The local source has nothing to sync"));
+            } else if !self.contains(only) {
+                return Err(IoError::new(ErrorKind::NotFound, "Does not exist"));
+            } else if !self.is_enabled(only) {
+                return Err(IoError::new(ErrorKind::InvalidInput, "Disabled"));
+            }
+        }
+
+        // The local source lives on disk and has nothing to sync
+        let targets = self.sources.iter()
+            .filter(|source| !source.is_local() && source.enabled)
+            .filter(|source| only.is_none_or(|only| only == source.name))
+            .cloned()
+            .collect();
+        return Ok(targets);
+    }
+
+    /// Syncs sources on up to `jobs` threads, reporting each one as soon as
+    /// it's done.
+    pub fn sync(&self, only: Option<&str>, jobs: usize, report: impl Fn(&str, IoResult<Synced>) + Sync) -> IoResult<()> {
+        sources::sync_all(self.sync_targets(only)?, jobs, report);
+        return Ok(());
+    }
+
+    /// Like [`TemplateStore::sync`], but only reports what a sync would change.
+    pub fn check(&self, only: Option<&str>, jobs: usize, report: impl Fn(&str, IoResult<Vec<Change>>) + Sync) -> IoResult<()> {
+        sources::check_all(self.sync_targets(only)?, jobs, report);
+        return Ok(());
+    }
+}
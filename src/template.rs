@@ -0,0 +1,1782 @@
+use crate::{archive, cargo_generate, cookiecutter, progress, sources::{self, auth::Auth, remote, Source, SourceStore}};
+use chrono::{Local, SecondsFormat};
+use dialoguer::{Confirm, Input, MultiSelect, Password, Select};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use heck::{ToKebabCase, ToLowerCamelCase, ToPascalCase, ToShoutySnakeCase, ToSnakeCase};
+use indicatif::{ProgressBar, ProgressFinish, ProgressStyle};
+use log::{debug, trace};
+use regex::Regex;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{borrow::Cow, collections::{BTreeMap, HashMap}, env, error::Error, fs, io::{Error as IoError, ErrorKind, Result as IoResult}, path::{Component, Path, PathBuf}, process::Command, sync::OnceLock};
+use tera::{Context, Tera};
+use ureq::http::StatusCode;
+use walkdir::WalkDir;
+
+pub const MANIFEST: &str = "template.toml";
+pub const IGNORE_FILE: &str = ".srcinitignore";
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Manifest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    // Another template (by name, looked up in the same source first) whose
+    // files and variables this one is layered on top of
+    pub extends: Option<String>,
+    // Other templates (looked up like base templates) generated underneath
+    // this one, each with a version requirement such as "^1.2" (or "*")
+    #[serde(default, skip_serializing_if = "toml::Table::is_empty")]
+    pub dependencies: toml::Table,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variables: Vec<Variable>,
+    // Values derived from the answers, each rendered once in the order
+    // they're declared (e.g. `crate_name = "{{ project_name | snake_case }}"`)
+    #[serde(default, skip_serializing_if = "toml::Table::is_empty")]
+    pub computed: toml::Table,
+    // Shell commands run in the template directory before anything is
+    // written, with the answers as a JSON object on stdin. Failing one
+    // rejects the answers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_gen: Vec<String>,
+    // Shell commands run in the output directory once it's generated
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_gen: Vec<String>,
+    // Seconds each hook may run for before it's killed
+    pub hook_timeout: Option<u64>,
+    // Printed once the project is generated, rendered like any file (e.g.
+    // "cd {{ project_name }} && cargo run")
+    pub post_message: Option<String>,
+    // SPDX identifier of a license written into generated projects as
+    // LICENSE, rendered like any file (e.g. "{{ license }}", to ask for it).
+    // "none" (or nothing) writes none, see license::LICENSES for the others
+    pub license: Option<String>,
+    // Sets of rules composed into the generated .gitignore, by name (e.g.
+    // ["rust", "macos"]), on top of the one the template has. Each is rendered
+    // like any file and can name several, separated by commas (e.g.
+    // "{{ editors | join(sep=',') }}"), see gitignore::GITIGNORES
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gitignore: Vec<String>,
+    // Also puts every answer under this name, for templates written for
+    // other tools (e.g. "cookiecutter", as in `{{ cookiecutter.project_name }}`)
+    pub namespace: Option<String>,
+    // What the files are written in when it isn't Tera, only "liquid" (as
+    // cargo-generate templates are) for now
+    pub syntax: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<FileRule>,
+    // Permission modes of files by their path in the template (e.g.
+    // `"run.sh" = "755"`), which otherwise keep their own. Mostly useful
+    // for templates written on Windows, where files don't carry any
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub modes: BTreeMap<String, String>,
+    // Answers the template is rendered with by `srcinit template-test`,
+    // optionally compared against the output they're expected to give
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<TestCase>,
+}
+
+// Per-path settings, where the path is relative to the template root and
+// written as it appears in the template (before placeholders are rendered)
+#[derive(Clone, Deserialize, Serialize)]
+pub struct FileRule {
+    pub path: String,
+    pub when: Option<String>,
+}
+
+// Paths are relative to the template root. Without a name, the test is named
+// after its values file
+#[derive(Clone, Deserialize, Serialize)]
+pub struct TestCase {
+    pub name: Option<String>,
+    pub values: String,
+    pub expected: Option<String>,
+}
+
+impl TestCase {
+    pub fn name(&self) -> String {
+        return self.name.clone().unwrap_or_else(|| Path::new(&self.values).file_stem().unwrap_or_default().to_string_lossy().to_string());
+    }
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Variable {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub kind: Kind,
+    pub default: Option<toml::Value>,
+    pub help: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+    // Secrets are typed in without being shown, and never written into
+    // lockfiles or logs
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub secret: bool,
+    // Only ask for the variable when this expression holds for the answers
+    // so far (e.g. `use_database`), otherwise it keeps its default
+    pub ask_when: Option<String>,
+    // A regular expression the whole value of a string must match
+    pub pattern: Option<String>,
+    // Bounds of an int, of the length of a string, or of how many choices
+    // of a multi-choice are picked
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+#[derive(Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    #[default]
+    String,
+    Bool,
+    Int,
+    Choice,
+    // Answered with any number of the choices, separated by commas
+    MultiChoice,
+}
+
+impl Kind {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Kind::String => "string",
+            Kind::Bool => "bool",
+            Kind::Int => "int",
+            Kind::Choice => "choice",
+            Kind::MultiChoice => "multi-choice",
+        };
+    }
+}
+
+impl Variable {
+    // Every variable ends up as a string once rendered, so non-string defaults
+    // (like `default = true`) are stored in their TOML representation
+    pub fn default_value(&self) -> Option<String> {
+        return match &self.default {
+            Some(toml::Value::String(value)) => Some(value.clone()),
+            Some(toml::Value::Array(values)) => Some(values.iter()
+                .map(|value| value.as_str().map(String::from).unwrap_or(value.to_string()))
+                .collect::<Vec<String>>()
+                .join(",")),
+            Some(value) => Some(value.to_string()),
+            None => None,
+        };
+    }
+
+    // Checks an answer against the type & constraints of the variable,
+    // explaining what's wrong with it otherwise
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        let within = |number: i64, what: &str| match (self.min, self.max) {
+            (Some(min), _) if number < min => Err(format!("{} must be at least {}", what, min)),
+            (_, Some(max)) if number > max => Err(format!("{} must be at most {}", what, max)),
+            _ => Ok(()),
+        };
+
+        return match self.kind {
+            Kind::String => {
+                if let Some(pattern) = &self.pattern {
+                    let regex = Regex::new(&format!("^(?:{})$", pattern))
+                        .map_err(|error| format!("invalid pattern: {}", error))?;
+                    if !regex.is_match(value) {
+                        return Err(format!("must match {}", pattern));
+                    }
+                }
+                within(value.chars().count() as i64, "length")
+            }
+            Kind::Bool if value == "true" || value == "false" => Ok(()),
+            Kind::Bool => Err(String::from("must be true or false")),
+            Kind::Int => match value.trim().parse::<i64>() {
+                Ok(number) => within(number, "value"),
+                Err(_) => Err(String::from("must be a whole number")),
+            },
+            Kind::Choice if self.choices.iter().any(|choice| choice == value) => Ok(()),
+            Kind::Choice => Err(format!("must be one of {}", self.choices.join(", "))),
+            Kind::MultiChoice => {
+                let picked = split_choices(value);
+                if let Some(unknown) = picked.iter().find(|picked| !self.choices.contains(picked)) {
+                    return Err(format!("\"{}\" isn't one of {}", unknown, self.choices.join(", ")));
+                }
+                within(picked.len() as i64, "number of choices")
+            }
+        };
+    }
+
+    pub fn prompt(&self, current: Option<&str>) -> IoResult<String> {
+        let label = self.help.clone().unwrap_or(self.name.clone());
+        let result = match self.kind {
+            Kind::String if self.secret => Password::new()
+                .with_prompt(label)
+                .allow_empty_password(current.is_some())
+                .validate_with(|value: &String| if value.is_empty() { Ok(()) } else { self.validate(value) })
+                .interact()
+                .map(|value| if value.is_empty() { current.unwrap_or_default().to_string() } else { value }),
+            Kind::String | Kind::Int => {
+                let mut input = Input::<String>::new()
+                    .with_prompt(label)
+                    .validate_with(|value: &String| self.validate(value));
+                if let Some(current) = current {
+                    input = input.default(current.to_string());
+                }
+                input.interact_text()
+            }
+            Kind::Bool => Confirm::new()
+                .with_prompt(label)
+                .default(current == Some("true"))
+                .interact()
+                .map(|value| value.to_string()),
+            Kind::Choice => {
+                let selected = current.and_then(|current| self.choices.iter().position(|choice| choice == current));
+                Select::new()
+                    .with_prompt(label)
+                    .items(&self.choices)
+                    .default(selected.unwrap_or(0))
+                    .interact()
+                    .map(|index| self.choices[index].clone())
+            }
+            Kind::MultiChoice => {
+                let current = current.map(split_choices).unwrap_or_default();
+                let checked: Vec<bool> = self.choices.iter().map(|choice| current.contains(choice)).collect();
+                loop {
+                    let picked = match MultiSelect::new().with_prompt(label.clone()).items(&self.choices).defaults(&checked).interact() {
+                        Ok(picked) => picked,
+                        Err(error) => break Err(error),
+                    };
+
+                    // The number of choices can only be checked once they're picked
+                    let value = picked.iter().map(|index| self.choices[*index].as_str()).collect::<Vec<&str>>().join(",");
+                    match self.validate(&value) {
+                        Ok(()) => break Ok(value),
+                        Err(reason) => eprintln!("{}", reason),
+                    }
+                }
+            }
+        };
+
+        return result.map_err(|dialoguer::Error::IO(error)| error);
+    }
+}
+
+impl Manifest {
+    // Templates without a manifest are still valid, they just don't
+    // carry any metadata or declare any variables
+    pub fn read(dir: &Path) -> IoResult<Manifest> {
+        let path = dir.join(MANIFEST);
+        if !path.is_file() {
+            return Ok(Manifest::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        return toml::from_str(&content)
+            .map_err(|error| IoError::new(ErrorKind::InvalidData, format!("invalid {}: {}", MANIFEST, error.message())));
+    }
+
+    // Layers this manifest on top of the one of the template it extends:
+    // its variables replace the base's ones of the same name (or come after
+    // them), and its hooks run after the base's ones
+    fn extend(self, base: Manifest) -> Manifest {
+        let mut variables = base.variables;
+        for variable in self.variables {
+            match variables.iter_mut().find(|existing| existing.name == variable.name) {
+                Some(existing) => *existing = variable,
+                None => variables.push(variable),
+            }
+        }
+
+        let mut tags = base.tags;
+        tags.extend(self.tags.into_iter().filter(|tag| !tags.contains(tag)).collect::<Vec<_>>());
+        let mut gitignore = base.gitignore;
+        gitignore.extend(self.gitignore.into_iter().filter(|name| !gitignore.contains(name)).collect::<Vec<_>>());
+        let mut computed = base.computed;
+        computed.extend(self.computed);
+        let mut modes = base.modes;
+        modes.extend(self.modes);
+        let mut dependencies = base.dependencies;
+        dependencies.extend(self.dependencies);
+
+        return Manifest {
+            name: self.name,
+            description: self.description.or(base.description),
+            version: self.version,
+            author: self.author.or(base.author),
+            extends: None,
+            dependencies,
+            tags,
+            variables,
+            computed,
+            pre_gen: base.pre_gen.into_iter().chain(self.pre_gen).collect(),
+            post_gen: base.post_gen.into_iter().chain(self.post_gen).collect(),
+            hook_timeout: self.hook_timeout.or(base.hook_timeout),
+            post_message: self.post_message.or(base.post_message),
+            license: self.license.or(base.license),
+            gitignore,
+            namespace: self.namespace.or(base.namespace),
+            syntax: self.syntax.or(base.syntax),
+            files: base.files.into_iter().chain(self.files).collect(),
+            modes,
+            // Tests are about the output of the layer declaring them
+            tests: self.tests,
+        };
+    }
+}
+
+// Files of a template that are never generated, imported or exported, listed
+// in its .srcinitignore with the same syntax as .gitignore
+pub struct Ignore(Gitignore);
+
+impl Ignore {
+    pub fn read(dir: &Path) -> IoResult<Ignore> {
+        let mut builder = GitignoreBuilder::new(dir);
+        let path = dir.join(IGNORE_FILE);
+        if path.is_file() {
+            if let Some(error) = builder.add(&path) {
+                return Err(IoError::new(ErrorKind::InvalidData, format!("invalid {}: {}", IGNORE_FILE, error)));
+            }
+        }
+
+        let ignore = builder.build()
+            .map_err(|error| IoError::new(ErrorKind::InvalidData, format!("invalid {}: {}", IGNORE_FILE, error)))?;
+        return Ok(Ignore(ignore));
+    }
+
+    // Whether a path relative to the template root is ignored, which it also
+    // is when one of its parent directories is
+    pub fn matches(&self, relative: &Path, is_dir: bool) -> bool {
+        return relative == Path::new(IGNORE_FILE) || self.0.matched_path_or_any_parents(relative, is_dir).is_ignore();
+    }
+}
+
+pub struct Template {
+    pub name: String,
+    pub source: String,
+    pub path: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Template {
+    pub fn qualified_name(&self) -> String {
+        // Templates given as a URL or a path are already named by it
+        if self.source.contains("://") || is_github(&self.source) {
+            return self.source.clone();
+        }
+        return format!("{}/{}", self.source, self.name);
+    }
+
+    pub fn load(source: &str, path: PathBuf) -> IoResult<Template> {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        if cookiecutter::is_cookiecutter(&path) {
+            let (manifest, root) = cookiecutter::convert(&path)?;
+            return Ok(Template { name, source: source.to_string(), path: root, manifest });
+        } else if cargo_generate::is_cargo_generate(&path) {
+            let manifest = cargo_generate::convert(&path)?;
+            return Ok(Template { name, source: source.to_string(), path, manifest });
+        }
+
+        let manifest = Manifest::read(&path)?;
+        return Ok(Template { name, source: source.to_string(), path, manifest });
+    }
+
+    // Manifest defaults, overridden by whatever the caller already knows
+    pub fn variables(&self, values: HashMap<String, String>) -> HashMap<String, String> {
+        let mut variables = HashMap::new();
+        for variable in &self.manifest.variables {
+            if let Some(default) = variable.default_value() {
+                variables.insert(variable.name.clone(), default);
+            }
+        }
+
+        variables.extend(values);
+        return variables;
+    }
+
+    // Asks for every declared variable in order (except the ones listed in
+    // skip), offering the value it currently has as the default answer
+    pub fn prompt(&self, mut variables: HashMap<String, String>, skip: &[&str]) -> IoResult<HashMap<String, String>> {
+        for variable in &self.manifest.variables {
+            if skip.contains(&variable.name.as_str()) || !self.is_asked(variable, &variables)? {
+                continue;
+            }
+
+            let value = variable.prompt(variables.get(&variable.name).map(String::as_str))?;
+            variables.insert(variable.name.clone(), value);
+        }
+
+        return Ok(variables);
+    }
+
+    // Everything files are rendered with: built-ins, then answers (hiding
+    // built-ins of the same name, and with answers to boolean variables
+    // turned back into real booleans so they can be used in conditions), then
+    // computed values, unless one of them was answered
+    pub fn context(&self, variables: &HashMap<String, String>) -> IoResult<Context> {
+        let mut context = self.answers(variables);
+        let mut tera = engine();
+        for (name, value) in &self.manifest.computed {
+            if variables.contains_key(name) {
+                continue;
+            }
+
+            match value {
+                toml::Value::String(expression) => {
+                    let rendered = render(&mut tera, expression, &context)
+                        .map_err(|error| IoError::new(error.kind(), format!("computed {}: {}", name, error)))?;
+                    context.insert(name, &rendered);
+                }
+                value => context.insert(name, value),
+            }
+        }
+
+        self.namespace(&mut context);
+        return Ok(context);
+    }
+
+    pub fn post_message(&self, variables: &HashMap<String, String>) -> IoResult<Option<String>> {
+        let Some(message) = &self.manifest.post_message else {
+            return Ok(None);
+        };
+
+        let rendered = render(&mut engine(), message, &self.context(variables)?)
+            .map_err(|error| IoError::new(error.kind(), format!("post_message: {}", error)))?;
+        return Ok(Some(rendered.trim_end().to_string()));
+    }
+
+    fn answers(&self, variables: &HashMap<String, String>) -> Context {
+        let mut context = Context::new();
+        for (key, value) in builtins() {
+            context.insert(*key, value);
+        }
+        for (key, value) in variables {
+            let kind = self.manifest.variables.iter()
+                .find(|variable| &variable.name == key)
+                .map(|variable| &variable.kind);
+            match kind {
+                Some(Kind::Bool) => context.insert(key, &(value == "true")),
+                Some(Kind::Int) => match value.trim().parse::<i64>() {
+                    Ok(number) => context.insert(key, &number),
+                    Err(_) => context.insert(key, value),
+                },
+                Some(Kind::MultiChoice) => context.insert(key, &split_choices(value)),
+                _ => context.insert(key, value),
+            }
+        }
+
+        self.namespace(&mut context);
+        return context;
+    }
+
+    // Template text (contents and paths) as Tera, which it already is unless
+    // the manifest says otherwise
+    pub fn translate<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        return match self.manifest.syntax.as_deref() {
+            Some(cargo_generate::SYNTAX) => Cow::Owned(cargo_generate::liquid(text)),
+            _ => Cow::Borrowed(text),
+        };
+    }
+
+    fn namespace(&self, context: &mut Context) {
+        if let Some(namespace) = &self.manifest.namespace {
+            context.remove(namespace);
+            let values = context.clone().into_json();
+            context.insert(namespace, &values);
+        }
+    }
+
+    // Number of files the template holds and their total size, not counting
+    // the manifest since it's never generated
+    pub fn footprint(&self) -> (usize, u64) {
+        let files = WalkDir::new(&self.path)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file() && entry.path() != self.path.join(MANIFEST))
+            .filter_map(|entry| entry.metadata().ok());
+
+        return files.fold((0, 0), |(count, size), metadata| (count + 1, size + metadata.len()));
+    }
+
+    // Validates every answer to a declared variable, see Variable::validate
+    pub fn validate(&self, variables: &HashMap<String, String>) -> IoResult<()> {
+        for variable in &self.manifest.variables {
+            if let Some(value) = variables.get(&variable.name) {
+                variable.validate(value).map_err(|reason| {
+                    IoError::new(ErrorKind::InvalidInput, format!("Invalid value for {}: {}", variable.name, reason))
+                })?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    // The modes declared in the manifest, parsed from octal
+    pub fn modes(&self) -> IoResult<HashMap<PathBuf, u32>> {
+        let mut modes = HashMap::new();
+        for (path, mode) in &self.manifest.modes {
+            let mode = u32::from_str_radix(mode, 8)
+                .ok()
+                .filter(|mode| *mode <= 0o7777)
+                .ok_or_else(|| IoError::new(ErrorKind::InvalidData, format!("invalid mode for {}: {}", path, mode)))?;
+            modes.insert(PathBuf::from(path), mode);
+        }
+
+        return Ok(modes);
+    }
+
+    // The answers with the values of secret variables masked, for showing
+    // them or logging them
+    pub fn redact(&self, variables: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut redacted = variables.clone();
+        for variable in self.manifest.variables.iter().filter(|variable| variable.secret) {
+            if let Some(value) = redacted.get_mut(&variable.name) {
+                *value = String::from("********");
+            }
+        }
+
+        return redacted;
+    }
+
+    // Whether a variable applies given the answers so far, see ask_when
+    pub fn is_asked(&self, variable: &Variable, variables: &HashMap<String, String>) -> IoResult<bool> {
+        let Some(ask_when) = &variable.ask_when else {
+            return Ok(true);
+        };
+
+        let asked = evaluate(&mut engine(), ask_when, &self.answers(variables))
+            .map_err(|error| IoError::new(error.kind(), format!("ask_when of {}: {}", variable.name, error)))?;
+        if !asked {
+            debug!("Not asking for {} ({} is false)", variable.name, ask_when);
+        }
+        return Ok(asked);
+    }
+
+    // Variables without an answer, leaving out the ones that don't apply (a
+    // condition that can't be evaluated counts as applying)
+    pub fn missing(&self, variables: &HashMap<String, String>) -> Vec<String> {
+        return self.manifest.variables.iter()
+            .filter(|variable| !variables.contains_key(&variable.name))
+            .filter(|variable| self.is_asked(variable, variables).unwrap_or(true))
+            .map(|variable| variable.name.clone())
+            .collect();
+    }
+}
+
+// Answers to multi-choice variables are kept as comma-separated lists
+pub fn split_choices(value: &str) -> Vec<String> {
+    return value.split(',').map(str::trim).filter(|choice| !choice.is_empty()).map(String::from).collect();
+}
+
+// Values every template can use without declaring them, worked out once
+pub fn builtins() -> &'static [(&'static str, String)] {
+    static BUILTINS: OnceLock<Vec<(&str, String)>> = OnceLock::new();
+    return BUILTINS.get_or_init(|| {
+        let now = Local::now();
+        let user = env::var("USER").or_else(|_| env::var("USERNAME")).unwrap_or_default();
+        let git_config = |key: &str| Command::new("git")
+            .args(["config", "--get", key])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .unwrap_or_default();
+
+        vec![
+            ("now", now.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            ("date", now.format("%Y-%m-%d").to_string()),
+            ("year", now.format("%Y").to_string()),
+            ("os", env::consts::OS.to_string()),
+            ("user", user),
+            ("git_name", git_config("user.name")),
+            ("git_email", git_config("user.email")),
+        ]
+    });
+}
+
+// Reads a flat table of answers from a TOML or JSON file (picked by its
+// extension), turning every non-string value into its textual form
+pub fn read_values(path: &Path) -> IoResult<HashMap<String, String>> {
+    let content = fs::read_to_string(path)?;
+    let invalid = |error: String| IoError::new(ErrorKind::InvalidData, format!("invalid values file: {}", error));
+
+    let mut values = HashMap::new();
+    if path.extension().is_some_and(|extension| extension == "json") {
+        let table: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&content)
+            .map_err(|error| invalid(error.to_string()))?;
+        for (key, value) in table {
+            let value = match value {
+                serde_json::Value::String(value) => value,
+                value => value.to_string(),
+            };
+            values.insert(key, value);
+        }
+    } else {
+        let table: toml::Table = toml::from_str(&content).map_err(|error| invalid(error.message().to_string()))?;
+        for (key, value) in table {
+            let value = match value {
+                toml::Value::String(value) => value,
+                value => value.to_string(),
+            };
+            values.insert(key, value);
+        }
+    }
+
+    return Ok(values);
+}
+
+pub fn list(source: &str) -> Vec<Template> {
+    let Ok(entries) = fs::read_dir(sources::templates_path(source)) else {
+        return Vec::new();
+    };
+
+    let mut templates: Vec<Template> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir() && !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|entry| Template::load(source, entry.path()).ok())
+        .collect();
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    return templates;
+}
+
+pub fn find(name: &str) -> IoResult<Template> {
+    return find_in(&SourceStore::load().unwrap_or_default(), name);
+}
+
+// A "template@1.2.0" name picks one of the versions kept by sync instead of
+// the current one
+pub fn find_in(sources: &SourceStore, name: &str) -> IoResult<Template> {
+    let template = match name.rsplit_once('@') {
+        Some((name, version)) => pinned(lookup(sources, name)?, version)?,
+        None => lookup(sources, name)?,
+    };
+    return assemble(sources, template);
+}
+
+fn pinned(template: Template, version: &str) -> IoResult<Template> {
+    let version = parse_version(version)
+        .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, format!("invalid version \"{}\"", version)))?;
+    if template.manifest.version.as_deref().and_then(parse_version).as_ref() == Some(&version) {
+        return Ok(template);
+    }
+
+    let path = versions_path(&template.source, &template.name).join(version.to_string());
+    if !path.is_dir() {
+        return Err(IoError::new(ErrorKind::NotFound, format!("version {} of \"{}\" isn't available", version, template.qualified_name())));
+    }
+
+    let mut pinned = Template::load(&template.source, path)?;
+    pinned.name = template.name;
+    return Ok(pinned);
+}
+
+// Merges a template with the templates it extends and depends on
+pub fn assemble(sources: &SourceStore, template: Template) -> IoResult<Template> {
+    let template = extend(sources, template)?;
+    return depend(sources, template);
+}
+
+// Templates extending another one are merged with it (and whatever it extends
+// in turn) into a directory of the cache, so they can be rendered like any
+// other template. Files of the template replace the base's ones at the same
+// path, and each layer's .srcinitignore only applies to its own files
+pub fn extend(sources: &SourceStore, template: Template) -> IoResult<Template> {
+    if template.manifest.extends.is_none() {
+        return Ok(template);
+    }
+
+    let layers = layers(sources, template, &mut Vec::new())?;
+    return merge(&layers, layers.last().unwrap());
+}
+
+// Templates with dependencies are merged with them the same way, every
+// dependency (after its own dependencies) layered underneath the template in
+// the order they're declared, and each of them only once
+fn depend(sources: &SourceStore, mut template: Template) -> IoResult<Template> {
+    if template.manifest.dependencies.is_empty() {
+        return Ok(template);
+    }
+
+    let mut layers = Vec::new();
+    dependencies(sources, &template, &mut layers, &mut vec![template.qualified_name()])?;
+    template.manifest.dependencies.clear();
+    layers.push(template);
+    return merge(&layers, layers.last().unwrap());
+}
+
+fn dependencies(sources: &SourceStore, template: &Template, layers: &mut Vec<Template>, chain: &mut Vec<String>) -> IoResult<()> {
+    for (name, requirement) in &template.manifest.dependencies {
+        let context = |error: IoError| {
+            IoError::new(error.kind(), format!("dependency \"{}\" of \"{}\": {}", name, template.qualified_name(), error))
+        };
+        let mut dependency = sibling(sources, template, name).and_then(|found| extend(sources, found)).map_err(context)?;
+        let qualified = dependency.qualified_name();
+        if chain.contains(&qualified) {
+            return Err(IoError::new(ErrorKind::InvalidData, format!("\"{}\" ends up depending on itself", qualified)));
+        } else if layers.iter().any(|layer| layer.qualified_name() == qualified) {
+            continue;
+        }
+
+        let requirement = requirement.as_str().unwrap_or_default();
+        let required = VersionReq::parse(requirement)
+            .map_err(|error| context(IoError::new(ErrorKind::InvalidData, format!("invalid version requirement: {}", error))))?;
+        let version = dependency.manifest.version.clone();
+        if !version.as_deref().and_then(parse_version).is_some_and(|version| required.matches(&version)) && required != VersionReq::STAR {
+            let version = version.unwrap_or(String::from("unversioned"));
+            return Err(context(IoError::new(ErrorKind::InvalidData, format!("requires {}, but found {}", requirement, version))));
+        }
+
+        chain.push(qualified);
+        dependencies(sources, &dependency, layers, chain)?;
+        chain.pop();
+        dependency.manifest.dependencies.clear();
+        layers.push(dependency);
+    }
+
+    return Ok(());
+}
+
+// Template versions don't have to be complete semantic versions, "2" and
+// "2.1" are read as "2.0.0" and "2.1.0"
+pub fn parse_version(version: &str) -> Option<Version> {
+    let version = version.trim().trim_start_matches('v');
+    let (core, suffix) = version.split_at(version.find(['-', '+']).unwrap_or(version.len()));
+    let padding = ".0".repeat(2usize.saturating_sub(core.matches('.').count()));
+    return Version::parse(&format!("{}{}{}", core, padding, suffix)).ok();
+}
+
+// Composes a template with add-on templates generated along with it, each
+// layered on top of the ones before it the same way as extending a template.
+// The result is still named after the template
+pub fn compose(template: Template, addons: Vec<Template>) -> IoResult<Template> {
+    if addons.is_empty() {
+        return Ok(template);
+    }
+
+    let layers: Vec<Template> = [template].into_iter().chain(addons).collect();
+    return merge(&layers, &layers[0]);
+}
+
+// Copies every layer into one directory of the cache, later layers replacing
+// the files of earlier ones, with a manifest merged the same way
+fn merge(layers: &[Template], named: &Template) -> IoResult<Template> {
+    let names: Vec<String> = layers.iter().map(Template::qualified_name).collect();
+    let checksum = remote::sha256(names.join("\n").as_bytes());
+    let path = sources::cache_path().join(".extended").join(&checksum[..16]).join(&named.name);
+    debug!("Merging {} into \"{}\"", names.join(", "), path.display());
+    if path.exists() {
+        fs::remove_dir_all(&path)?;
+    }
+
+    let mut manifest = Manifest::default();
+    for layer in layers {
+        copy_dir(&layer.path, &path, Some(&Ignore::read(&layer.path)?))?;
+        manifest = layer.manifest.clone().extend(manifest);
+    }
+    manifest.name = named.manifest.name.clone();
+    manifest.version = named.manifest.version.clone();
+    manifest.description = named.manifest.description.clone().or(manifest.description);
+    manifest.author = named.manifest.author.clone().or(manifest.author);
+    fs::write(path.join(MANIFEST), toml::to_string(&manifest).map_err(IoError::other)?)?;
+
+    let mut merged = Template::load(&named.source, path)?;
+    merged.name = named.name.clone();
+    return Ok(merged);
+}
+
+// The template along with every template it extends, the base-most first
+fn layers(sources: &SourceStore, template: Template, chain: &mut Vec<String>) -> IoResult<Vec<Template>> {
+    let Some(extends) = template.manifest.extends.clone() else {
+        return Ok(vec![template]);
+    };
+
+    chain.push(template.qualified_name());
+    let base = sibling(sources, &template, &extends).map_err(|error| {
+        IoError::new(error.kind(), format!("base template \"{}\" of \"{}\": {}", extends, template.qualified_name(), error))
+    })?;
+    if chain.contains(&base.qualified_name()) {
+        return Err(IoError::new(ErrorKind::InvalidData, format!("\"{}\" ends up extending itself", base.qualified_name())));
+    }
+
+    let mut layers = layers(sources, base, chain)?;
+    layers.push(template);
+    return Ok(layers);
+}
+
+// Templates named by another one are looked up in its source first
+fn sibling(sources: &SourceStore, template: &Template, name: &str) -> IoResult<Template> {
+    if name.contains('/') {
+        return lookup(sources, name);
+    }
+    return lookup(sources, &format!("{}/{}", template.source, name)).or_else(|_| lookup(sources, name));
+}
+
+fn lookup(sources: &SourceStore, name: &str) -> IoResult<Template> {
+    // Names also come from manifests (extends & dependencies), so neither
+    // half may point anywhere but a template directory of a source
+    let (source, template) = name.split_once('/').map_or((None, name), |(source, template)| (Some(source), template));
+    if !source.is_none_or(is_valid_name) || !is_valid_name(template) {
+        return Err(IoError::new(ErrorKind::InvalidInput, format!("invalid template name \"{}\"", name)));
+    }
+
+    // A fully-qualified "source/template" name only ever looks in that source
+    if let Some((source, name)) = name.split_once('/') {
+        let Some(source) = sources.get(source) else {
+            return Err(IoError::new(ErrorKind::NotFound, format!("source \"{}\" does not exist", source)));
+        };
+        if !source.enabled {
+            return Err(IoError::new(ErrorKind::NotFound, format!("source \"{}\" is disabled", source.name)));
+        }
+
+        let path = source.templates_path().join(name);
+        if !path.is_dir() {
+            return Err(IoError::new(ErrorKind::NotFound, "template not found"));
+        }
+        return Template::load(&source.name, path);
+    }
+
+    // Otherwise, the local source is always searched first, then every
+    // other enabled source in the order they were declared in the sources
+    // file (which is the order the store keeps them in)
+    for source in sources.iter().filter(|source| source.enabled) {
+        let path = source.templates_path().join(name);
+        if path.is_dir() {
+            return Template::load(&source.name, path);
+        }
+    }
+
+    return Err(IoError::new(ErrorKind::NotFound, "template not found"));
+}
+
+// A Tera instance with srcinit's own filters registered, so the same
+// `{{ project_name | snake_case }}` works in file contents and paths alike
+pub fn engine() -> Tera {
+    let mut tera = Tera::default();
+    tera.register_filter("snake_case", case_filter(|value| value.to_snake_case()));
+    tera.register_filter("kebab_case", case_filter(|value| value.to_kebab_case()));
+    tera.register_filter("pascal_case", case_filter(|value| value.to_pascal_case()));
+    tera.register_filter("camel_case", case_filter(|value| value.to_lower_camel_case()));
+    tera.register_filter("screaming_snake_case", case_filter(|value| value.to_shouty_snake_case()));
+    return tera;
+}
+
+fn case_filter(convert: fn(&str) -> String) -> impl tera::Filter {
+    return move |value: &tera::Value, _: &HashMap<String, tera::Value>| match value.as_str() {
+        Some(value) => Ok(tera::Value::String(convert(value))),
+        None => Err(tera::Error::msg("case filters can only be applied to strings")),
+    };
+}
+
+// Evaluates a Tera expression (e.g. `use_docker and not minimal`) for truthiness
+pub fn evaluate(tera: &mut Tera, expression: &str, context: &Context) -> IoResult<bool> {
+    let result = render(tera, &format!("{{% if {} %}}true{{% endif %}}", expression), context)?;
+    return Ok(result == "true");
+}
+
+// Renders a single piece of text on its own, without a template around it,
+// with the built-ins and the given variables (as strings)
+pub fn render_text(content: &str, variables: &HashMap<String, String>) -> IoResult<String> {
+    let mut context = Context::new();
+    for (key, value) in builtins() {
+        context.insert(*key, value);
+    }
+    for (key, value) in variables {
+        context.insert(key, value);
+    }
+
+    return render(&mut engine(), content, &context);
+}
+
+pub fn render(tera: &mut Tera, content: &str, context: &Context) -> IoResult<String> {
+    return tera.render_str(content, context).map_err(|error| {
+        // Tera nests the actual reason (e.g. an unknown variable) in the
+        // error sources, so the whole chain is needed for a useful message
+        let mut message = error.to_string();
+        let mut source = error.source();
+        while let Some(cause) = source {
+            message.push_str(&format!(": {}", cause));
+            source = cause.source();
+        }
+
+        IoError::new(ErrorKind::InvalidData, message)
+    });
+}
+
+// Text files never contain NUL bytes, and anything we'd render has to be
+// valid UTF-8 anyway, so either check failing means the file is binary
+pub fn is_binary(content: &[u8]) -> bool {
+    return content.iter().take(8000).any(|byte| *byte == 0) || std::str::from_utf8(content).is_err();
+}
+
+// What happens to files that already exist in the output directory. Without
+// a policy, generating into a non-empty directory is refused altogether
+#[derive(Clone, Copy, PartialEq)]
+pub enum Conflict {
+    Skip,
+    Overwrite,
+    Prompt,
+    Backup,
+}
+
+// Limits generation to part of a template, by globs matched against the
+// paths of generated files (e.g. `.github/**`)
+#[derive(Default)]
+pub struct Filter {
+    only: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl Filter {
+    pub fn new(only: &[String], exclude: &[String]) -> IoResult<Filter> {
+        return Ok(Filter { only: globs(only)?, exclude: globs(exclude)? });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.only.is_none() && self.exclude.is_none();
+    }
+
+    pub fn matches(&self, target: &Path) -> bool {
+        return self.only.as_ref().is_none_or(|only| only.is_match(target))
+            && !self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(target));
+    }
+}
+
+fn globs(patterns: &[String]) -> IoResult<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|error| IoError::new(ErrorKind::InvalidInput, format!("invalid glob {}: {}", pattern, error.kind())))?;
+        builder.add(glob);
+    }
+    return builder.build().map(Some).map_err(|error| IoError::new(ErrorKind::InvalidInput, error.to_string()));
+}
+
+pub struct Entry {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub is_dir: bool,
+    // Symlinks are recreated as they are, pointing to the same path
+    pub is_link: bool,
+}
+
+// Works out every file and directory a template produces, with placeholders
+// in their names already rendered, relative to the output directory.
+// Following symlinks treats them as the files or directories they point to,
+// which is what always happens on Windows, where creating them needs rights
+// most users don't have
+pub fn plan(template: &Template, tera: &mut Tera, context: &Context, follow_symlinks: bool, filter: &Filter) -> IoResult<Vec<Entry>> {
+    let follow_symlinks = follow_symlinks || cfg!(not(unix));
+    let mut entries = Vec::new();
+    let mut targets: HashMap<PathBuf, (PathBuf, bool)> = HashMap::new();
+    let ignore = Ignore::read(&template.path)?;
+
+    // Excluding a directory also excludes everything inside of it
+    let mut excluded = Vec::new();
+    for rule in &template.manifest.files {
+        let Some(when) = &rule.when else {
+            continue;
+        };
+
+        let included = evaluate(tera, when, context)
+            .map_err(|error| IoError::new(error.kind(), format!("condition for {}: {}", rule.path, error)))?;
+        if !included {
+            debug!("Excluding \"{}\" ({} is false)", rule.path, when);
+            excluded.push(PathBuf::from(&rule.path));
+        }
+    }
+
+    for entry in WalkDir::new(&template.path).min_depth(1).follow_links(follow_symlinks).sort_by_file_name() {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(&template.path).unwrap();
+        if relative == Path::new(MANIFEST) || excluded.iter().any(|path| relative.starts_with(path)) {
+            continue;
+        }
+        if ignore.matches(relative, entry.file_type().is_dir()) {
+            trace!("Ignoring \"{}\"", relative.display());
+            continue;
+        }
+
+        let mut target = PathBuf::from(render(tera, &template.translate(&relative.to_string_lossy()), context)
+            .map_err(|error| IoError::new(error.kind(), format!("{}: {}", relative.display(), error)))?);
+        // Liquid templates mark the files to render with an extension of their
+        // own (e.g. Cargo.toml.liquid), which the generated ones go without
+        if template.manifest.syntax.as_deref() == Some(cargo_generate::SYNTAX) {
+            if let Some(stripped) = target.to_string_lossy().strip_suffix(cargo_generate::EXTENSION).filter(|stripped| !stripped.is_empty()) {
+                target = PathBuf::from(stripped);
+            }
+        }
+        if !is_contained(&target) {
+            return Err(IoError::new(ErrorKind::InvalidData, format!("\"{}\" renders to \"{}\", outside of the output", relative.display(), target.display())));
+        }
+        let is_dir = entry.file_type().is_dir();
+
+        // Directories are allowed to render into the same place (their contents
+        // simply merge), but anything involving a file must stay unique
+        if let Some((existing, existing_is_dir)) = targets.get(&target) {
+            if !is_dir || !existing_is_dir {
+                return Err(IoError::new(ErrorKind::AlreadyExists, format!(
+                    "\"{}\" and \"{}\" both render to \"{}\"",
+                    existing.display(), relative.display(), target.display()
+                )));
+            }
+        }
+
+        targets.insert(target.clone(), (relative.to_path_buf(), is_dir));
+        entries.push(Entry { source: entry.path().to_path_buf(), target, is_dir, is_link: entry.path_is_symlink() && !follow_symlinks });
+    }
+
+    // Filtering leaves out files, and with them directories that end up
+    // holding none
+    if !filter.is_empty() {
+        entries.retain(|entry| entry.is_dir || filter.matches(&entry.target));
+        let files: Vec<PathBuf> = entries.iter().filter(|entry| !entry.is_dir).map(|entry| entry.target.clone()).collect();
+        entries.retain(|entry| !entry.is_dir || files.iter().any(|file| file.starts_with(&entry.target)));
+    }
+
+    return Ok(entries);
+}
+
+/// Whether a path relative to a directory stays inside of it, which rendered
+/// names (placeholders and answers alike) have to.
+pub fn is_contained(path: &Path) -> bool {
+    return path.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+}
+
+// The size of every file of a plan once it's generated, which means rendering
+// text files in memory. Symlinks are counted as empty
+pub fn sizes(template: &Template, tera: &mut Tera, context: &Context, entries: &[Entry]) -> IoResult<Vec<u64>> {
+    let mut sizes = Vec::new();
+    for entry in entries.iter().filter(|entry| !entry.is_dir) {
+        if entry.is_link {
+            sizes.push(0);
+            continue;
+        }
+
+        let content = fs::read(&entry.source)?;
+        if is_binary(&content) {
+            sizes.push(content.len() as u64);
+            continue;
+        }
+
+        let relative = entry.source.strip_prefix(&template.path).unwrap();
+        let rendered = render(tera, &template.translate(&String::from_utf8(content).unwrap()), context)
+            .map_err(|error| IoError::new(error.kind(), format!("{}: {}", relative.display(), error)))?;
+        sizes.push(rendered.len() as u64);
+    }
+
+    return Ok(sizes);
+}
+
+/// A file srcinit writes into the project itself (like LICENSE), given what
+/// the file holds so far: the template's version of it once rendered, or
+/// else the one already in the output. Returns what to write, if anything.
+pub type Extra<'a> = (PathBuf, Box<dyn Fn(Option<String>) -> IoResult<Option<String>> + 'a>);
+
+#[allow(clippy::too_many_arguments)]
+pub fn generate(template: &Template, output: &Path, variables: &HashMap<String, String>, conflict: Option<Conflict>, follow_symlinks: bool, filter: &Filter, extras: &[Extra]) -> IoResult<()> {
+    debug!("Generating \"{}\" into \"{}\"", template.qualified_name(), output.display());
+    let occupied = fs::read_dir(output).is_ok_and(|mut entries| entries.next().is_some());
+    if occupied && conflict.is_none() {
+        return Err(IoError::new(ErrorKind::AlreadyExists, format!("\"{}\" isn't empty", output.display())));
+    }
+
+    trace!("Variables: {:?}", template.redact(variables));
+    let context = template.context(variables)?;
+    let mut tera = engine();
+    let entries = plan(template, &mut tera, &context, follow_symlinks, filter)?;
+
+    // Everything is rendered into a staging directory next to the output
+    // first, so that a failure halfway (a template error, a full disk...)
+    // leaves the output directory exactly as it was
+    let staging = staging_path(output);
+    let result = stage(template, &mut tera, &context, &entries, output, &staging, conflict)
+        .and_then(|staged| stage_extras(extras, staged, output, &staging))
+        .and_then(|staged| commit(&entries, &staged, output, &staging, conflict));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    return result;
+}
+
+fn staging_path(output: &Path) -> PathBuf {
+    let name = output.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let parent = output.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    return parent.join(format!(".{}.srcinit-{:08x}", name, fastrand::u32(..)));
+}
+
+// Renders every entry into the staging directory, returning the files to be
+// moved into the output (existing files that are kept are left out)
+fn stage(template: &Template, tera: &mut Tera, context: &Context, entries: &[Entry], output: &Path, staging: &Path, conflict: Option<Conflict>) -> IoResult<Vec<PathBuf>> {
+    // Cleared however generation ends, so a failure isn't shown half-drawn
+    let files = entries.iter().filter(|entry| !entry.is_dir).count();
+    let progress = ProgressBar::with_draw_target(Some(files as u64), progress::target())
+        .with_style(ProgressStyle::with_template("{spinner} {pos}/{len} files {wide_msg}").unwrap())
+        .with_finish(ProgressFinish::AndClear);
+
+    let mut staged = Vec::new();
+    let modes = template.modes()?;
+    fs::create_dir_all(staging)?;
+    for entry in entries.iter().filter(|entry| !entry.is_dir) {
+        progress.set_message(entry.target.display().to_string());
+        progress.inc(1);
+        if output.join(&entry.target).exists() && !progress.suspend(|| resolve(&entry.target, conflict))? {
+            debug!("Keeping existing \"{}\"", entry.target.display());
+            continue;
+        }
+
+        // Binary files (images, fonts, archives...) are copied as they are,
+        // since rendering would either fail or corrupt them
+        let target = staging.join(&entry.target);
+        let relative = entry.source.strip_prefix(&template.path).unwrap();
+        fs::create_dir_all(target.parent().unwrap())?;
+        staged.push(entry.target.clone());
+        if entry.is_link {
+            trace!("Linking \"{}\"", entry.target.display());
+            link(&entry.source, &target)?;
+            continue;
+        }
+
+        let content = fs::read(&entry.source)?;
+        if is_binary(&content) {
+            trace!("Copying binary file \"{}\"", entry.target.display());
+            fs::write(&target, content)?;
+        } else {
+            trace!("Rendering \"{}\" -> \"{}\"", relative.display(), entry.target.display());
+            let content = String::from_utf8(content).unwrap();
+            let rendered = render(tera, &template.translate(&content), context)
+                .map_err(|error| IoError::new(error.kind(), format!("{}: {}", relative.display(), error)))?;
+            fs::write(&target, rendered)?;
+        }
+
+        // Generated files keep the mode of their template file (so scripts
+        // stay executable), unless the manifest says otherwise
+        match modes.get(relative) {
+            Some(mode) => set_mode(&target, *mode)?,
+            None => fs::set_permissions(&target, fs::metadata(&entry.source)?.permissions())?,
+        }
+    }
+
+    return Ok(staged);
+}
+
+// Extra files are staged along with the template's, so they're only ever
+// written if everything else was too
+fn stage_extras(extras: &[Extra], mut staged: Vec<PathBuf>, output: &Path, staging: &Path) -> IoResult<Vec<PathBuf>> {
+    for (file, content) in extras {
+        let target = staging.join(file);
+        let existing = match staged.contains(file) {
+            true => Some(fs::read_to_string(&target)?),
+            false => fs::read_to_string(output.join(file)).ok(),
+        };
+
+        if let Some(content) = content(existing)? {
+            trace!("Writing \"{}\"", file.display());
+            fs::create_dir_all(target.parent().unwrap())?;
+            fs::write(&target, content)?;
+            if !staged.contains(file) {
+                staged.push(file.clone());
+            }
+        }
+    }
+
+    return Ok(staged);
+}
+
+#[cfg(unix)]
+fn link(source: &Path, target: &Path) -> IoResult<()> {
+    return std::os::unix::fs::symlink(fs::read_link(source)?, target);
+}
+
+#[cfg(not(unix))]
+fn link(source: &Path, target: &Path) -> IoResult<()> {
+    return fs::copy(source, target).map(|_| ());
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> IoResult<()> {
+    use std::os::unix::fs::PermissionsExt;
+    return fs::set_permissions(path, fs::Permissions::from_mode(mode));
+}
+
+// Modes don't mean anything elsewhere, they're only kept in exports
+#[cfg(not(unix))]
+fn set_mode(_: &Path, _: u32) -> IoResult<()> {
+    return Ok(());
+}
+
+// Moves the staged files into place. A new output directory is just the
+// staging directory renamed, which is atomic
+fn commit(entries: &[Entry], staged: &[PathBuf], output: &Path, staging: &Path, conflict: Option<Conflict>) -> IoResult<()> {
+    if !output.exists() {
+        for entry in entries.iter().filter(|entry| entry.is_dir) {
+            fs::create_dir_all(staging.join(&entry.target))?;
+        }
+        if let Some(parent) = output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        return fs::rename(staging, output);
+    }
+
+    for entry in entries.iter().filter(|entry| entry.is_dir) {
+        fs::create_dir_all(output.join(&entry.target))?;
+    }
+    for file in staged {
+        let target = output.join(file);
+        if conflict == Some(Conflict::Backup) && target.exists() {
+            let mut backup = target.as_os_str().to_owned();
+            backup.push(".bak");
+            debug!("Backing up \"{}\"", file.display());
+            fs::rename(&target, backup)?;
+        }
+
+        fs::create_dir_all(target.parent().unwrap())?;
+        fs::rename(staging.join(file), target)?;
+    }
+
+    return Ok(());
+}
+
+// Decides whether an existing file gets replaced (backups are only made once
+// everything was rendered successfully)
+fn resolve(relative: &Path, conflict: Option<Conflict>) -> IoResult<bool> {
+    return match conflict {
+        None | Some(Conflict::Skip) => Ok(false),
+        Some(Conflict::Overwrite | Conflict::Backup) => Ok(true),
+        Some(Conflict::Prompt) => Confirm::new()
+            .with_prompt(format!("Overwrite \"{}\"?", relative.display()))
+            .default(false)
+            .interact()
+            .map_err(|dialoguer::Error::IO(error)| error),
+    };
+}
+
+/// Whether a template can be named that in a source: names are directory
+/// names there, so they can't be empty, contain slashes or start with a dot
+/// (which rules out "." and ".." too).
+pub fn is_valid_name(name: &str) -> bool {
+    return !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\']);
+}
+
+fn invalid_name(name: &str) -> IoError {
+    return IoError::new(ErrorKind::InvalidInput, format!("invalid template name \"{}\"", name));
+}
+
+pub fn import(file: &Path) -> IoResult<Template> {
+    let name = if file.is_dir() {
+        fs::canonicalize(file)?.file_name().unwrap().to_string_lossy().to_string()
+    } else if let Some(stem) = archive::stem(file) {
+        stem
+    } else {
+        return Err(IoError::new(ErrorKind::InvalidInput, "expected a directory, .zip or .tar.gz"));
+    };
+
+    if file.is_dir() {
+        // A name declared in the manifest takes precedence over the directory name
+        let name = Manifest::read(file)?.name.unwrap_or(name);
+        if !is_valid_name(&name) {
+            return Err(invalid_name(&name));
+        }
+        let path = sources::templates_path("local").join(&name);
+        if path.exists() {
+            return Err(IoError::new(ErrorKind::AlreadyExists, format!("template \"{}\" already exists", name)));
+        }
+
+        copy_dir(file, &path, Some(&Ignore::read(file)?))?;
+        return Template::load("local", path);
+    }
+
+    // Archives are extracted next to the store first, since the template
+    // may well be nested inside of them (see root)
+    let staging = sources::templates_path("local").join(format!(".{}.import", name));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    archive::extract(file, &staging)?;
+    let root = root(&staging, &name)?;
+
+    let manifest = Manifest::read(&root);
+    let name = match manifest {
+        Ok(manifest) => manifest.name.unwrap_or(name),
+        Err(error) => {
+            fs::remove_dir_all(&staging)?;
+            return Err(error);
+        }
+    };
+
+    if !is_valid_name(&name) {
+        fs::remove_dir_all(&staging)?;
+        return Err(invalid_name(&name));
+    }
+    let path = sources::templates_path("local").join(&name);
+    if path.exists() {
+        fs::remove_dir_all(&staging)?;
+        return Err(IoError::new(ErrorKind::AlreadyExists, format!("template \"{}\" already exists", name)));
+    }
+
+    let ignore = match Ignore::read(&root) {
+        Ok(ignore) => ignore,
+        Err(error) => {
+            fs::remove_dir_all(&staging)?;
+            return Err(error);
+        }
+    };
+    copy_dir(&root, &path, Some(&ignore))?;
+    fs::remove_dir_all(&staging)?;
+
+    return Template::load("local", path);
+}
+
+// Most archives wrap everything inside a top-level directory named after the
+// archive (or holding the manifest, like the ones produced by export), which
+// is then where the template actually is
+fn root(extracted: &Path, name: &str) -> IoResult<PathBuf> {
+    let entries: Vec<PathBuf> = fs::read_dir(extracted)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    let wrapped = entries.len() == 1
+        && entries[0].is_dir()
+        && (entries[0].file_name().unwrap() == name || entries[0].join(MANIFEST).is_file());
+
+    return Ok(if wrapped { entries[0].clone() } else { extracted.to_path_buf() });
+}
+
+// Whether a template is given as a path (e.g. ./my-template) rather than by
+// name, which "source/template" names make ambiguous for relative paths
+// unless they start with a dot
+pub fn is_path(name: &str) -> bool {
+    let path = Path::new(name);
+    return path.is_absolute() || path.starts_with(".") || path.starts_with("..");
+}
+
+// A template read in place from a directory, as authors work on it
+pub fn open(path: &Path) -> IoResult<Template> {
+    let path = fs::canonicalize(path)?;
+    if !path.is_dir() {
+        return Err(IoError::new(ErrorKind::InvalidInput, format!("\"{}\" isn't a directory", path.display())));
+    }
+
+    let mut template = Template::load(&format!("file://{}", path.display()), path)?;
+    template.name = template.manifest.name.clone().unwrap_or(template.name);
+    return assemble(&SourceStore::load().unwrap_or_default(), template);
+}
+
+// Clones a template given as a git URL into the given directory, which is up
+// to the caller to remove once done with it
+pub fn fetch(url: &str, path: &Path) -> IoResult<Template> {
+    let remote = sources::git_remote(url)
+        .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, format!("\"{}\" isn't a git URL", url)))?;
+    debug!("Fetching \"{}\" into \"{}\"", remote, path.display());
+    sources::clone(remote, path)?;
+
+    // The repository itself is the template, its history isn't part of it
+    fs::remove_dir_all(path.join(".git"))?;
+    let mut template = Template::load(url, path.to_path_buf())?;
+    let repository = remote.trim_end_matches('/').trim_end_matches(".git");
+    let repository = repository.rsplit(['/', ':']).next().unwrap_or(repository);
+    template.name = template.manifest.name.clone().unwrap_or(repository.to_string());
+    return assemble(&SourceStore::load().unwrap_or_default(), template);
+}
+
+// Whether a template is given as the URL of an archive, e.g.
+// https://example.com/template.tar.gz (optionally with #sha256=<checksum>)
+pub fn is_archive_url(name: &str) -> bool {
+    let url = name.split(['#', '?']).next().unwrap();
+    return (url.starts_with("https://") || url.starts_with("http://")) && archive::is_archive(Path::new(url));
+}
+
+// Downloads and extracts a template given as the URL of an archive into the
+// given directory, which is up to the caller to remove once done with it. The
+// archive has to match the checksum in a #sha256= fragment, if there is one
+pub fn download(url: &str, path: &Path) -> IoResult<Template> {
+    let (url, checksum) = match url.split_once('#') {
+        Some((url, fragment)) => match fragment.strip_prefix("sha256=") {
+            Some(checksum) => (url, Some(checksum)),
+            None => return Err(IoError::new(ErrorKind::InvalidInput, format!("expected #sha256=<checksum> instead of #{}", fragment))),
+        },
+        None => (url, None),
+    };
+
+    let progress = ProgressBar::with_draw_target(None, progress::target())
+        .with_style(ProgressStyle::with_template("{spinner} {bytes}/{total_bytes} ({bytes_per_sec})").unwrap())
+        .with_finish(ProgressFinish::AndClear);
+    debug!("Downloading \"{}\" into \"{}\"", url, path.display());
+    // Not from any configured source, so with the default settings
+    let data = remote::fetch(url, &Source::new("", url), None, &progress)?;
+    if checksum.is_some_and(|checksum| !remote::sha256(&data).eq_ignore_ascii_case(checksum)) {
+        return Err(IoError::new(ErrorKind::InvalidData, format!("checksum mismatch for \"{}\"", url)));
+    }
+
+    let file = path.join(url.split('?').next().unwrap().rsplit('/').next().unwrap());
+    let stem = archive::stem(&file).unwrap();
+    fs::create_dir_all(path)?;
+    fs::write(&file, data)?;
+    let staging = path.join("template");
+    archive::extract(&file, &staging)?;
+
+    let mut template = Template::load(url, root(&staging, &stem)?)?;
+    template.name = template.manifest.name.clone().unwrap_or(stem);
+    return assemble(&SourceStore::load().unwrap_or_default(), template);
+}
+
+// Whether a template is given as a GitHub repository, e.g. gh:owner/repo, or
+// gh:owner/repo/path/to/template for a template in one of its directories
+pub fn is_github(name: &str) -> bool {
+    return name.strip_prefix("gh:").is_some_and(|repository| repository.split('/').filter(|part| !part.is_empty()).count() >= 2);
+}
+
+// Downloads a template given as a GitHub repository (see is_github) through
+// the API into the given directory, which is up to the caller to remove once
+// done with it. Private repositories need a token in GH_TOKEN or GITHUB_TOKEN,
+// and GITHUB_API_URL points to a GitHub Enterprise server instead
+pub fn download_github(name: &str, path: &Path) -> IoResult<Template> {
+    let parts: Vec<&str> = name.trim_start_matches("gh:").split('/').filter(|part| !part.is_empty()).collect();
+    let (repository, directory) = (parts[..2].join("/"), parts[2..].join("/"));
+    if parts.iter().any(|part| *part == "." || *part == "..") {
+        return Err(IoError::new(ErrorKind::InvalidInput, format!("invalid repository path \"{}\"", name)));
+    }
+
+    let api = env::var("GITHUB_API_URL").unwrap_or(String::from("https://api.github.com"));
+    let url = format!("{}/repos/{}/tarball", api.trim_end_matches('/'), repository);
+    let auth = ["GH_TOKEN", "GITHUB_TOKEN"].iter()
+        .find_map(|variable| env::var(variable).ok().filter(|token| !token.trim().is_empty()))
+        .map(Auth::Bearer);
+
+    let progress = ProgressBar::with_draw_target(None, progress::target())
+        .with_style(ProgressStyle::with_template("{spinner} {bytes}/{total_bytes} ({bytes_per_sec})").unwrap())
+        .with_finish(ProgressFinish::AndClear);
+    debug!("Downloading \"{}\" into \"{}\"", url, path.display());
+    // GitHub answers 404 rather than 401 for private repositories
+    let data = remote::fetch(&url, &Source::new("", url.as_str()), auth.as_ref(), &progress).map_err(|error| match remote::is_status(&error, StatusCode::NOT_FOUND) {
+        true if auth.is_none() => IoError::new(ErrorKind::NotFound, format!("repository \"{}\" not found (private ones need GH_TOKEN or GITHUB_TOKEN)", repository)),
+        true => IoError::new(ErrorKind::NotFound, format!("repository \"{}\" not found", repository)),
+        false => error,
+    })?;
+
+    let file = path.join("repository.tar.gz");
+    fs::create_dir_all(path)?;
+    fs::write(&file, data)?;
+    let staging = path.join("repository");
+    archive::extract(&file, &staging)?;
+
+    // The tarball wraps the repository in a directory named after its commit
+    let mut entries = fs::read_dir(&staging)?.filter_map(|entry| entry.ok()).map(|entry| entry.path());
+    let root = match (entries.next(), entries.next()) {
+        (Some(root), None) if root.is_dir() => root.join(&directory),
+        _ => return Err(IoError::new(ErrorKind::InvalidData, format!("unexpected tarball layout for \"{}\"", repository))),
+    };
+    if !root.is_dir() {
+        return Err(IoError::new(ErrorKind::NotFound, format!("\"{}\" has no directory \"{}\"", repository, directory)));
+    }
+
+    let mut template = Template::load(name, root)?;
+    template.name = template.manifest.name.clone().unwrap_or(parts[parts.len() - 1].to_string());
+    return assemble(&SourceStore::load().unwrap_or_default(), template);
+}
+
+// Where the copy of a template taken when generating is kept, see snapshot
+pub fn snapshot_path(checksum: &str) -> PathBuf {
+    return sources::cache_path().join(".snapshots").join(checksum);
+}
+
+// Keeps a copy of a template as it was when generating, so upgrades can later
+// tell what a project started out as. Copies are named after the checksum of
+// the template's files, and so shared by every project generated from it
+pub fn snapshot(template: &Template) -> IoResult<String> {
+    let mut files: Vec<PathBuf> = WalkDir::new(&template.path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.path().strip_prefix(&template.path).unwrap().to_path_buf())
+        .collect();
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for file in &files {
+        hasher.update(file.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(fs::read(template.path.join(file))?);
+        hasher.update([0]);
+    }
+    let checksum: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    let path = snapshot_path(&checksum);
+    if !path.is_dir() {
+        debug!("Keeping a snapshot of \"{}\" in \"{}\"", template.qualified_name(), path.display());
+        let partial = path.with_extension("partial");
+        if partial.exists() {
+            fs::remove_dir_all(&partial)?;
+        }
+        copy_dir(&template.path, &partial, None)?;
+        fs::rename(&partial, &path)?;
+    }
+
+    return Ok(checksum);
+}
+
+// How many versions of each template sync keeps, unless the source's
+// keep_versions setting says otherwise
+pub const KEEP_VERSIONS: usize = 3;
+
+// Where the versions of a template kept by sync are, one directory each
+pub fn versions_path(source: &str, name: &str) -> PathBuf {
+    return sources::cache_path().join(".versions").join(source).join(name);
+}
+
+// Every version of a template that can be generated, newest first: the
+// current one along with the ones kept by sync
+pub fn versions(template: &Template) -> Vec<Version> {
+    let mut versions: Vec<Version> = fs::read_dir(versions_path(&template.source, &template.name))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| parse_version(&entry.file_name().to_string_lossy()))
+        .chain(template.manifest.version.as_deref().and_then(parse_version))
+        .collect();
+    versions.sort_by(|a, b| b.cmp(a));
+    versions.dedup();
+    return versions;
+}
+
+// Keeps a copy of the current version of every template of a source, then
+// drops all but the `keep` most recent versions of each of them. Unversioned
+// templates only ever have their current version
+pub fn keep_versions(source: &str, keep: usize) -> IoResult<()> {
+    for template in list(source) {
+        let Some(version) = template.manifest.version.as_deref().and_then(parse_version) else {
+            continue;
+        };
+
+        let path = versions_path(source, &template.name).join(version.to_string());
+        if !path.is_dir() {
+            debug!("Keeping version {} of \"{}\"", version, template.qualified_name());
+            let partial = path.with_file_name(format!(".{}.partial", version));
+            if partial.exists() {
+                fs::remove_dir_all(&partial)?;
+            }
+            copy_dir(&template.path, &partial, None)?;
+            fs::rename(&partial, &path)?;
+        }
+    }
+
+    let Ok(entries) = fs::read_dir(sources::cache_path().join(".versions").join(source)) else {
+        return Ok(());
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let mut kept: Vec<(Version, PathBuf)> = fs::read_dir(entry.path())?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| Some((parse_version(&entry.file_name().to_string_lossy())?, entry.path())))
+            .collect();
+        kept.sort_by(|(a, _), (b, _)| b.cmp(a));
+        for (version, path) in kept.into_iter().skip(keep) {
+            debug!("Dropping version {} of \"{}/{}\"", version, source, entry.file_name().to_string_lossy());
+            fs::remove_dir_all(path)?;
+        }
+    }
+
+    return Ok(());
+}
+
+// Copies a directory as is (symlinks included), leaving out what's ignored
+pub fn copy_dir(from: &Path, to: &Path, ignore: Option<&Ignore>) -> IoResult<()> {
+    for entry in WalkDir::new(from).follow_links(cfg!(not(unix))) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(from).unwrap();
+        if ignore.is_some_and(|ignore| ignore.matches(relative, entry.file_type().is_dir())) {
+            continue;
+        }
+
+        let target = to.join(relative);
+
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(target)?;
+        } else if entry.path_is_symlink() {
+            link(entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+
+    return Ok(());
+}
+
+pub fn export(name: &str, output: &Path) -> IoResult<PathBuf> {
+    // Every exported archive carries a manifest, so templates that never had
+    // one get a minimal manifest naming them
+    let template = find(name)?;
+    let modes = template.modes()?;
+    let mut manifest = template.manifest;
+    manifest.name = manifest.name.or(Some(template.name.clone()));
+    let manifest = toml::to_string(&manifest).map_err(IoError::other)?;
+
+    fs::create_dir_all(output)?;
+    let file = output.join(format!("{}.tar.gz", template.name));
+    let ignore = Ignore::read(&template.path)?;
+    archive::pack(&template.path, &template.name, &file, &[(MANIFEST, manifest.as_bytes())], &modes, |relative, is_dir| {
+        ignore.matches(relative, is_dir)
+    })?;
+    return Ok(file);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("srcinit-test-{}-{:08x}", name, fastrand::u32(..)));
+        fs::create_dir_all(&dir).unwrap();
+        return dir;
+    }
+
+    fn write_template(dir: &Path, files: &[(&str, &str)]) -> Template {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(MANIFEST), "name = \"t\"\n").unwrap();
+        for (path, content) in files {
+            fs::create_dir_all(dir.join(path).parent().unwrap()).unwrap();
+            fs::write(dir.join(path), content).unwrap();
+        }
+        return Template::load("local", dir.to_path_buf()).unwrap();
+    }
+
+    fn variables(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        return pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+    }
+
+    #[test]
+    fn keeps_rendered_paths_inside_the_output() {
+        let root = scratch("escape");
+        let template = write_template(&root.join("template"), &[("{{ \"..\" }}/escaped.txt", "out")]);
+        let error = generate(&template, &root.join("out/project"), &HashMap::new(), None, false, &Filter::default(), &[]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(!root.join("out/escaped.txt").exists());
+        assert!(!root.join("out/project").exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn keeps_answers_in_paths_inside_the_output() {
+        let root = scratch("escape-answer");
+        let template = write_template(&root.join("template"), &[("{{ project_name }}/main.txt", "out")]);
+        for name in ["../x", "/tmp/x", "a/../../x"] {
+            let error = generate(&template, &root.join("out/project"), &variables(&[("project_name", name)]), None, false, &Filter::default(), &[]).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData, "{}", name);
+        }
+        assert!(!root.join("out/x").exists());
+
+        // Going down (or nowhere) is fine
+        generate(&template, &root.join("out/project"), &variables(&[("project_name", "a/./b")]), None, false, &Filter::default(), &[]).unwrap();
+        assert_eq!(fs::read_to_string(root.join("out/project/a/b/main.txt")).unwrap(), "out");
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    fn staged_leftovers(parent: &Path) -> Vec<String> {
+        return fs::read_dir(parent).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.contains(".srcinit-"))
+            .collect();
+    }
+
+    #[test]
+    fn generates_through_staging() {
+        let root = scratch("staging");
+        let template = write_template(&root.join("template"), &[("{{ project_name }}.txt", "Hello {{ project_name | upper }}\n"), (".gitignore", "/target\n")]);
+        let gitignore: Extra = (PathBuf::from(".gitignore"), Box::new(|existing: Option<String>| Ok(Some(existing.unwrap_or_default() + "*.log\n"))));
+        let license: Extra = (PathBuf::from("LICENSE"), Box::new(|_| Ok(Some(String::from("MIT\n")))));
+        let output = root.join("out/project");
+        generate(&template, &output, &variables(&[("project_name", "demo")]), None, false, &Filter::default(), &[gitignore, license]).unwrap();
+
+        assert_eq!(fs::read_to_string(output.join("demo.txt")).unwrap(), "Hello DEMO\n");
+        assert_eq!(fs::read_to_string(output.join(".gitignore")).unwrap(), "/target\n*.log\n");
+        assert_eq!(fs::read_to_string(output.join("LICENSE")).unwrap(), "MIT\n");
+        assert!(!output.join(MANIFEST).exists());
+        assert!(staged_leftovers(&root.join("out")).is_empty());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn rolls_back_failed_generation() {
+        let root = scratch("rollback");
+        let template = write_template(&root.join("template"), &[("a.txt", "fine"), ("b.txt", "{{ missing }}")]);
+        let output = root.join("project");
+        assert!(generate(&template, &output, &HashMap::new(), None, false, &Filter::default(), &[]).is_err());
+        assert!(!output.exists());
+        assert!(staged_leftovers(&root).is_empty());
+
+        // Nothing of a project that's already there is touched either, even
+        // by extras, which are only written along with everything else
+        fs::create_dir_all(&output).unwrap();
+        fs::write(output.join("a.txt"), "mine").unwrap();
+        let license: Extra = (PathBuf::from("LICENSE"), Box::new(|_| Ok(Some(String::from("MIT\n")))));
+        assert!(generate(&template, &output, &HashMap::new(), Some(Conflict::Overwrite), false, &Filter::default(), &[license]).is_err());
+        assert_eq!(fs::read_to_string(output.join("a.txt")).unwrap(), "mine");
+        assert!(!output.join("LICENSE").exists());
+        assert!(staged_leftovers(&root).is_empty());
+
+        let failing: Extra = (PathBuf::from("LICENSE"), Box::new(|_| Err(IoError::other("no license"))));
+        let template = write_template(&root.join("fine"), &[("a.txt", "fine")]);
+        assert!(generate(&template, &output, &HashMap::new(), Some(Conflict::Overwrite), false, &Filter::default(), &[failing]).is_err());
+        assert_eq!(fs::read_to_string(output.join("a.txt")).unwrap(), "mine");
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn handles_conflicts() {
+        let root = scratch("conflict");
+        let template = write_template(&root.join("template"), &[("a.txt", "new"), ("b.txt", "new")]);
+        let generate_with = |conflict: Option<Conflict>| {
+            let output = root.join(format!("project-{:08x}", fastrand::u32(..)));
+            fs::create_dir_all(&output).unwrap();
+            fs::write(output.join("a.txt"), "mine").unwrap();
+            return generate(&template, &output, &HashMap::new(), conflict, false, &Filter::default(), &[]).map(|_| output);
+        };
+
+        // Without a policy, nothing is generated into a non-empty directory
+        assert_eq!(generate_with(None).err().map(|error| error.kind()), Some(ErrorKind::AlreadyExists));
+
+        // Skipping keeps existing files, while new ones are still written
+        let output = generate_with(Some(Conflict::Skip)).unwrap();
+        assert_eq!(fs::read_to_string(output.join("a.txt")).unwrap(), "mine");
+        assert_eq!(fs::read_to_string(output.join("b.txt")).unwrap(), "new");
+
+        let output = generate_with(Some(Conflict::Overwrite)).unwrap();
+        assert_eq!(fs::read_to_string(output.join("a.txt")).unwrap(), "new");
+        assert!(!output.join("a.txt.bak").exists());
+
+        let output = generate_with(Some(Conflict::Backup)).unwrap();
+        assert_eq!(fs::read_to_string(output.join("a.txt")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(output.join("a.txt.bak")).unwrap(), "mine");
+        assert!(!output.join("b.txt.bak").exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn looks_up_names_inside_sources_only() {
+        // Rejected before anything is read, so the real sources are never
+        // looked at
+        let sources = SourceStore::default();
+        for name in ["local/../../x", "local/..", "../x", "..", "local/.hidden", "local/a\\b", "local/", "/x"] {
+            assert_eq!(lookup(&sources, name).err().map(|error| error.kind()), Some(ErrorKind::InvalidInput), "{}", name);
+        }
+    }
+
+    #[test]
+    fn looks_up_dependencies_inside_sources_only() {
+        let root = scratch("dependencies");
+        for name in ["..", "../x", "local/../../x"] {
+            let dir = root.join(format!("{:08x}", fastrand::u32(..)));
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(MANIFEST), format!("name = \"t\"\n\n[dependencies]\n\"{}\" = \"*\"\n", name)).unwrap();
+            let template = Template::load("local", dir).unwrap();
+            assert_eq!(depend(&SourceStore::default(), template).err().map(|error| error.kind()), Some(ErrorKind::InvalidInput), "{}", name);
+        }
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
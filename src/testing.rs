@@ -0,0 +1,113 @@
+use crate::{sources, template::{TestCase, Template}, Generator};
+use std::{fs, io::{Error as IoError, ErrorKind, Result as IoResult}, path::{Path, PathBuf}};
+use walkdir::WalkDir;
+
+/// How the output of a test differs from what it's expected to be, by path
+/// relative to the output.
+#[derive(Debug)]
+pub enum Difference {
+    /// Expected, but not generated.
+    Missing(PathBuf),
+    /// Generated, but not expected.
+    Unexpected(PathBuf),
+    /// Generated with other contents than expected.
+    Changed(PathBuf),
+}
+
+impl Difference {
+    pub fn kind(&self) -> &'static str {
+        return match self {
+            Difference::Missing(_) => "missing",
+            Difference::Unexpected(_) => "unexpected",
+            Difference::Changed(_) => "changed",
+        };
+    }
+
+    pub fn path(&self) -> &Path {
+        return match self {
+            Difference::Missing(path) | Difference::Unexpected(path) | Difference::Changed(path) => path,
+        };
+    }
+}
+
+/// Renders a template with the answers of one of its tests, into a scratch
+/// directory that's removed afterwards.
+///
+/// Rendering has to succeed without asking anything, and when the test has
+/// expected output, every file has to match it. With `update`, the expected
+/// output is replaced with the rendered one instead. Hooks never run.
+///
+/// Paths of the test are relative to `root`, the template's own directory,
+/// which for a template merged with others isn't where it's rendered from.
+pub fn run(template: &Template, root: &Path, test: &TestCase, update: bool) -> IoResult<Vec<Difference>> {
+    let expected = test.expected.as_ref().map(|expected| root.join(expected));
+    if let Some(expected) = expected.as_ref().filter(|_| update) {
+        if expected.exists() {
+            fs::remove_dir_all(expected)?;
+        }
+        render(template, root, test, expected)?;
+        return Ok(Vec::new());
+    }
+
+    let scratch = sources::cache_path().join(".tests").join(format!("{:08x}", fastrand::u32(..)));
+    let output = scratch.join(test.name());
+    let result = render(template, root, test, &output).and_then(|_| match &expected {
+        Some(expected) => compare(&output, expected),
+        None => Ok(Vec::new()),
+    });
+    if scratch.exists() {
+        fs::remove_dir_all(&scratch)?;
+    }
+
+    return result;
+}
+
+// The project is named after the test (and so is its directory), unless the
+// values say otherwise. It's generated like any project, LICENSE and
+// .gitignore included, but nothing's written into a lockfile, since the same
+// output has to come out of every run
+fn render(template: &Template, root: &Path, test: &TestCase, output: &Path) -> IoResult<()> {
+    let mut generator = Generator::new(template, output);
+    generator.define("project_name", &test.name()).skip_lock();
+    generator.values(&root.join(&test.values))?;
+
+    let missing = generator.missing();
+    if !missing.is_empty() {
+        return Err(IoError::new(ErrorKind::InvalidInput, format!("missing variables: {}", missing.join(", "))));
+    }
+
+    return generator.generate();
+}
+
+fn compare(output: &Path, expected: &Path) -> IoResult<Vec<Difference>> {
+    if !expected.is_dir() {
+        return Err(IoError::new(ErrorKind::NotFound, format!("expected output \"{}\" does not exist", expected.display())));
+    }
+
+    let generated = files(output);
+    let wanted = files(expected);
+    let mut differences = Vec::new();
+    for file in &wanted {
+        if !generated.contains(file) {
+            differences.push(Difference::Missing(file.clone()));
+        } else if fs::read(output.join(file))? != fs::read(expected.join(file))? {
+            differences.push(Difference::Changed(file.clone()));
+        }
+    }
+    for file in generated.into_iter().filter(|file| !wanted.contains(file)) {
+        differences.push(Difference::Unexpected(file));
+    }
+
+    return Ok(differences);
+}
+
+fn files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| !entry.file_type().is_dir())
+        .map(|entry| entry.path().strip_prefix(dir).unwrap().to_path_buf())
+        .collect();
+    files.sort();
+    return files;
+}
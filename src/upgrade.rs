@@ -0,0 +1,181 @@
+use crate::{lock::Lock, template::{self, Filter, Template}};
+use log::debug;
+use std::{collections::{BTreeSet, HashMap}, env, fs, io::{Error as IoError, ErrorKind, Result as IoResult}, path::{Path, PathBuf}};
+use walkdir::WalkDir;
+
+/// What an upgrade did to a file of the project.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Added,
+    Updated,
+    Removed,
+    /// Changed by both the template and the user, and merged cleanly.
+    Merged,
+    /// Changed by both in ways that can't be merged. Text files are left
+    /// with conflict markers, anything else is left as the user had it.
+    Conflict,
+}
+
+// Renders the project as it was first generated (from the snapshot named in
+// its lockfile) and as the new template would generate it, then merges the
+// difference between both into the project's files
+pub fn upgrade(project: &Path, template: &Template, variables: &HashMap<String, String>) -> IoResult<Vec<(PathBuf, Outcome)>> {
+    let lock = Lock::read(project)?;
+    let scratch = env::temp_dir().join(format!("srcinit-upgrade-{:08x}", fastrand::u32(..)));
+    let result = merge_all(project, lock.snapshot().as_ref(), template, variables, &lock, &scratch);
+    if scratch.exists() {
+        fs::remove_dir_all(&scratch)?;
+    }
+    return result;
+}
+
+// Without the original template (e.g. the cache was cleared, taking its
+// snapshot along), the project is merged as if it started out empty, so
+// anything that differs is a conflict
+fn merge_all(project: &Path, original: Option<&Template>, template: &Template, variables: &HashMap<String, String>, lock: &Lock, scratch: &Path) -> IoResult<Vec<(PathBuf, Outcome)>> {
+    let base = scratch.join("base");
+    if let Some(original) = original {
+        // Secrets never make it into the lockfile, so the current answers
+        // stand in for them
+        let mut answers = variables.clone();
+        answers.extend(lock.variables.clone());
+        template::generate(original, &base, &answers, None, false, &Filter::default(), &[])?;
+    } else {
+        debug!("No snapshot of \"{}\" left to upgrade from", lock.qualified_name());
+    }
+
+    let new = scratch.join("new");
+    template::generate(template, &new, variables, None, false, &Filter::default(), &[])?;
+
+    let files: BTreeSet<PathBuf> = files(&base).into_iter().chain(files(&new)).collect();
+    let mut outcomes = Vec::new();
+    for file in files {
+        if !template::is_contained(&file) {
+            return Err(IoError::new(ErrorKind::InvalidData, format!("\"{}\" is outside of the project", file.display())));
+        }
+        let target = project.join(&file);
+        let ours = fs::read(&target).ok();
+        let outcome = merge(&target, fs::read(base.join(&file)).ok(), ours, fs::read(new.join(&file)).ok())?;
+        if let Some(outcome) = outcome {
+            debug!("{:?} \"{}\"", outcome, file.display());
+            outcomes.push((file, outcome));
+        }
+    }
+
+    return Ok(outcomes);
+}
+
+fn files(dir: &Path) -> Vec<PathBuf> {
+    return WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.path().strip_prefix(dir).unwrap().to_path_buf())
+        .collect();
+}
+
+fn merge(target: &Path, base: Option<Vec<u8>>, ours: Option<Vec<u8>>, theirs: Option<Vec<u8>>) -> IoResult<Option<Outcome>> {
+    // Nothing changed in the template, or the user already made the same change
+    if theirs == base || ours == theirs {
+        return Ok(None);
+    }
+
+    // Untouched by the user, so the template's version simply replaces it
+    if ours == base {
+        let Some(theirs) = theirs else {
+            fs::remove_file(target)?;
+            return Ok(Some(Outcome::Removed));
+        };
+
+        fs::create_dir_all(target.parent().unwrap())?;
+        fs::write(target, theirs)?;
+        return Ok(Some(if ours.is_none() { Outcome::Added } else { Outcome::Updated }));
+    }
+
+    let (Some(ours), Some(theirs)) = (ours, theirs) else {
+        return Ok(Some(Outcome::Conflict));
+    };
+    let base = base.unwrap_or_default();
+    let (Ok(base), Ok(ours), Ok(theirs)) = (String::from_utf8(base), String::from_utf8(ours), String::from_utf8(theirs)) else {
+        return Ok(Some(Outcome::Conflict));
+    };
+
+    return match diffy::merge(&base, &ours, &theirs) {
+        Ok(merged) => {
+            fs::write(target, merged)?;
+            Ok(Some(Outcome::Merged))
+        }
+        Err(conflicted) => {
+            fs::write(target, conflicted)?;
+            Ok(Some(Outcome::Conflict))
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Generator;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("srcinit-test-{}-{:08x}", name, fastrand::u32(..)));
+        fs::create_dir_all(&dir).unwrap();
+        return dir;
+    }
+
+    fn write_template(dir: &Path, version: &str, content: &str) {
+        let manifest = format!("name = \"secretive\"\nversion = \"{}\"\n\n[[variables]]\nname = \"api_token\"\nsecret = true\n", version);
+        fs::write(dir.join(template::MANIFEST), manifest).unwrap();
+        fs::write(dir.join("config.txt"), content).unwrap();
+    }
+
+    // Both versions are loaded straight from their directories, rather than
+    // from a snapshot in the cache
+    #[test]
+    fn upgrades_templates_with_secrets() {
+        let root = scratch("upgrade");
+        let (old, new, project) = (root.join("1.0.0"), root.join("1.1.0"), root.join("project"));
+        fs::create_dir_all(&old).unwrap();
+        fs::create_dir_all(&new).unwrap();
+
+        write_template(&old, "1.0.0", "token = {{ api_token }}\n");
+        let original = Template::load("local", old).unwrap();
+        let mut generator = Generator::new(&original, &project);
+        generator.define("api_token", "s3cret").skip_lock().generate().unwrap();
+        let lock = Lock::new(&original, generator.variables());
+        assert!(!lock.variables.contains_key("api_token"));
+
+        write_template(&new, "1.1.0", "token = {{ api_token }}\nretries = 3\n");
+        let template = Template::load("local", new).unwrap();
+        let variables = HashMap::from([(String::from("api_token"), String::from("s3cret"))]);
+        let outcomes = merge_all(&project, Some(&original), &template, &variables, &lock, &root.join("scratch")).unwrap();
+        assert_eq!(outcomes, vec![(PathBuf::from("config.txt"), Outcome::Updated)]);
+        assert_eq!(fs::read_to_string(project.join("config.txt")).unwrap(), "token = s3cret\nretries = 3\n");
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn merges_changes_from_both_sides() {
+        let root = scratch("merge");
+        let target = root.join("file.txt");
+        fs::write(&target, "a\nB\nc\n").unwrap();
+        let outcome = merge(&target, Some(b"a\nb\nc\n".to_vec()), Some(b"a\nB\nc\n".to_vec()), Some(b"a\nb\nc\nd\n".to_vec())).unwrap();
+        assert_eq!(outcome, Some(Outcome::Merged));
+        assert_eq!(fs::read_to_string(&target).unwrap(), "a\nB\nc\nd\n");
+
+        // The user already made the change the template makes
+        assert_eq!(merge(&target, Some(b"a".to_vec()), Some(b"b".to_vec()), Some(b"b".to_vec())).unwrap(), None);
+
+        let outcome = merge(&target, Some(b"a\n".to_vec()), Some(b"b\n".to_vec()), Some(b"c\n".to_vec())).unwrap();
+        assert_eq!(outcome, Some(Outcome::Conflict));
+        assert!(fs::read_to_string(&target).unwrap().contains("<<<<<<<"));
+
+        fs::remove_file(&target).unwrap();
+        assert_eq!(merge(&target, None, None, Some(b"new".to_vec())).unwrap(), Some(Outcome::Added));
+        assert_eq!(merge(&target, Some(b"new".to_vec()), Some(b"new".to_vec()), None).unwrap(), Some(Outcome::Removed));
+        assert!(!target.exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,21 @@
+use crate::sources;
+use std::{io::Result as IoResult, path::Path, process::Command};
+
+fn git(dir: &Path, args: &[&str]) -> IoResult<()> {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(dir).args(args);
+    return sources::run_git(command);
+}
+
+// Turns a freshly generated project into a git repository, with everything
+// in it as the first commit and optionally an "origin" remote
+pub fn init(dir: &Path, message: &str, remote: Option<&str>) -> IoResult<()> {
+    git(dir, &["init", "--quiet"])?;
+    git(dir, &["add", "--all"])?;
+    git(dir, &["commit", "--quiet", "--message", message])?;
+    if let Some(remote) = remote {
+        git(dir, &["remote", "add", "origin", remote])?;
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,39 @@
+use log::trace;
+use std::{collections::hash_map::DefaultHasher, hash::{Hash, Hasher}, path::PathBuf, thread, time::Duration};
+use walkdir::WalkDir;
+
+/// Calls `changed` right away, then again whenever a file under any of the
+/// paths is added, removed or modified, checking every `interval`. Never
+/// returns, watching goes on until the process is interrupted.
+///
+/// Paths are polled rather than watched through the OS, which is plenty
+/// for the few files of a template.
+pub fn watch(paths: &[PathBuf], interval: Duration, mut changed: impl FnMut()) -> ! {
+    let mut last = None;
+    loop {
+        let current = fingerprint(paths);
+        if last != Some(current) {
+            trace!("Change detected in {:?}", paths);
+            last = Some(current);
+            changed();
+        }
+        thread::sleep(interval);
+    }
+}
+
+// Hash of the path, size and modification time of every file, which changes
+// along with any of them
+fn fingerprint(paths: &[PathBuf]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for path in paths {
+        for entry in WalkDir::new(path).sort_by_file_name().into_iter().filter_map(|entry| entry.ok()) {
+            entry.path().hash(&mut hasher);
+            if let Ok(metadata) = entry.metadata() {
+                metadata.len().hash(&mut hasher);
+                metadata.modified().ok().hash(&mut hasher);
+            }
+        }
+    }
+
+    return hasher.finish();
+}